@@ -0,0 +1,50 @@
+//! Checks that `replace_with` updates a large payload in place.
+//!
+//! Run with `cargo bench`. If the closure's argument and result were copied through the stack,
+//! a 64 KiB payload would make `replace_with` much slower than writing the field directly; the
+//! functions are `#[inline(never)]` so their code can also be inspected with `cargo rustc
+//! --release --bench in_place -- --emit asm`.
+
+extern crate untagged_option;
+
+use std::hint::black_box;
+use std::time::Instant;
+use untagged_option::UntaggedOption;
+
+const SIZE: usize = 64 * 1024;
+const ITERATIONS: u32 = 100_000;
+
+#[inline(never)]
+fn bump_replace_with(opt: &mut UntaggedOption<[u8; SIZE]>) {
+    unsafe {
+        opt.replace_with(|mut bytes| {
+            bytes[100] = bytes[100].wrapping_add(1);
+            bytes
+        });
+    }
+}
+
+#[inline(never)]
+fn bump_direct(opt: &mut UntaggedOption<[u8; SIZE]>) {
+    unsafe {
+        let bytes = opt.as_mut();
+        bytes[100] = bytes[100].wrapping_add(1);
+    }
+}
+
+fn time(name: &str, opt: &mut UntaggedOption<[u8; SIZE]>, f: fn(&mut UntaggedOption<[u8; SIZE]>)) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f(black_box(&mut *opt));
+    }
+    let elapsed = start.elapsed();
+    println!("{:<12} {:>8.1} ns/iter", name, elapsed.as_nanos() as f64 / ITERATIONS as f64);
+}
+
+fn main() {
+    let mut opt = UntaggedOption::<[u8; SIZE]>::none_boxed();
+    opt.write([0; SIZE]);
+    time("direct", &mut opt, bump_direct);
+    time("replace_with", &mut opt, bump_replace_with);
+    unsafe { opt.take(); }
+}
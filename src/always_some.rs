@@ -0,0 +1,60 @@
+//! Deserialization of `UntaggedOption` fields with serde's derive.
+//!
+//! A field that always holds a value once its struct is deserialized can use
+//! `#[serde(deserialize_with = "untagged_option::always_some::deserialize")]`: the `T` is
+//! deserialized and stored in a full option.
+//!
+//! There is no matching `serialize`, so the module cannot be used with `#[serde(with = ...)]`.
+//! Serde's derive calls the `serialize_with` function without `unsafe`, so it would have to be
+//! a safe function, and a safe function cannot read the payload of an option that may be empty.
+//! Instead, point `serialize_with` at a function of your own that knows why the field is full,
+//! and call the unsafe [`serialize_some`] from it:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Calibration {
+//!     #[serde(
+//!         serialize_with = "serialize_offset",
+//!         deserialize_with = "untagged_option::always_some::deserialize"
+//!     )]
+//!     offset: UntaggedOption<i32>,
+//! }
+//!
+//! fn serialize_offset<S: Serializer>(opt: &UntaggedOption<i32>, s: S) -> Result<S::Ok, S::Error> {
+//!     // Safe: `Calibration` is only created through deserialization, which fills `offset`.
+//!     unsafe { opt.serialize_some(s) }
+//! }
+//! ```
+//!
+//! Fields that may be empty are serialized through [`with_flag`], see [`serde_support`].
+//!
+//! Available with the `serde` feature.
+//!
+//! [`serialize_some`]: crate::UntaggedOption::serialize_some
+//! [`with_flag`]: crate::UntaggedOption::with_flag
+//! [`serde_support`]: crate::serde_support
+
+use serde::{Deserialize, Deserializer};
+
+use UntaggedOption;
+
+/// Deserializes a `T` into an option holding it, for use with `#[serde(deserialize_with)]`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<UntaggedOption<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    UntaggedOption::deserialize_some(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, U32Deserializer};
+
+    #[test]
+    fn deserializes_full_option() {
+        let opt: UntaggedOption<u32> = deserialize(U32Deserializer::<Error>::new(7)).unwrap();
+        assert_eq!(unsafe { opt.into_inner() }, 7);
+    }
+}
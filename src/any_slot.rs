@@ -0,0 +1,204 @@
+//! A single slot that can hold a value of any type.
+
+use core::any::TypeId;
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+use UntaggedOption;
+
+/// The alignment of the inline storage of an [`AnySlot`].
+///
+/// [`AnySlot`]: crate::AnySlot
+pub const MAX_ALIGN: usize = 16;
+
+/// Inline byte storage aligned to [`MAX_ALIGN`].
+///
+/// [`MAX_ALIGN`]: crate::any_slot::MAX_ALIGN
+#[repr(C, align(16))]
+struct Storage<const S: usize>([u8; S]);
+
+/// Type information of the value stored in an [`AnySlot`].
+///
+/// [`AnySlot`]: crate::AnySlot
+#[derive(Clone, Copy)]
+struct Occupant {
+    type_id: TypeId,
+    drop: unsafe fn(*mut u8),
+}
+
+unsafe fn drop_as<T>(ptr: *mut u8) {
+    ptr::drop_in_place(ptr as *mut T);
+}
+
+/// A slot that stores a value of any `'static`, `Send` and `Sync` type inside `S` bytes of inline
+/// storage.
+///
+/// The storage is untagged; the slot records the [`TypeId`] and destructor of the value it holds
+/// next to it, so values can only be read back as the type they were stored as. Types that do not
+/// fit into `S` bytes, or need an alignment of more than [`MAX_ALIGN`], are rejected at compile
+/// time.
+///
+/// The stored value can be accessed and dropped through a slot that has been sent to another
+/// thread, so only values that are `Send` and `Sync` can be stored:
+///
+/// ```compile_fail
+/// # extern crate untagged_option;
+/// # use untagged_option::AnySlot;
+/// # use std::rc::Rc;
+/// # fn main() {
+/// let mut slot = AnySlot::<8>::new();
+/// slot.insert(Rc::new(()));
+/// # }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::AnySlot;
+/// # fn main() {
+/// let mut slot = AnySlot::<8>::new();
+/// slot.insert(7u32);
+/// assert_eq!(slot.get::<u32>(), Some(&7));
+/// assert_eq!(slot.get::<i32>(), None);
+///
+/// slot.insert('x');
+/// assert_eq!(slot.take::<char>(), Some('x'));
+/// assert!(slot.is_empty());
+/// # }
+/// ```
+///
+/// [`TypeId`]: core::any::TypeId
+/// [`MAX_ALIGN`]: crate::any_slot::MAX_ALIGN
+pub struct AnySlot<const S: usize = 32> {
+    occupant: Option<Occupant>,
+    storage: UntaggedOption<Storage<S>>,
+}
+
+impl<const S: usize> AnySlot<S> {
+    /// Creates an empty slot.
+    pub const fn new() -> Self {
+        AnySlot {
+            occupant: None,
+            storage: UntaggedOption::none(),
+        }
+    }
+
+    /// Returns whether the slot holds no value.
+    pub fn is_empty(&self) -> bool {
+        self.occupant.is_none()
+    }
+
+    /// Returns the `TypeId` of the stored value, if any.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.occupant.map(|occupant| occupant.type_id)
+    }
+
+    /// Returns whether the slot holds a value of type `T`.
+    pub fn holds<T: 'static>(&self) -> bool {
+        self.type_id() == Some(TypeId::of::<T>())
+    }
+
+    /// Stores `value`, dropping the value previously stored in the slot, whatever its type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        const {
+            assert!(mem::size_of::<T>() <= S, "type too large for AnySlot");
+            assert!(mem::align_of::<T>() <= MAX_ALIGN, "type too strictly aligned for AnySlot");
+        }
+
+        self.clear();
+        unsafe {
+            ptr::write(self.storage.as_mut_ptr() as *mut T, value);
+        }
+        self.occupant = Some(Occupant {
+            type_id: TypeId::of::<T>(),
+            drop: drop_as::<T>,
+        });
+    }
+
+    /// Returns a reference to the stored value if it is of type `T`.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        if self.holds::<T>() {
+            unsafe { Some(&*(self.storage.as_ptr() as *const T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the stored value if it is of type `T`.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        if self.holds::<T>() {
+            unsafe { Some(&mut *(self.storage.as_mut_ptr() as *mut T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Removes and returns the stored value if it is of type `T`.
+    ///
+    /// Values of other types are left in place.
+    pub fn take<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        if self.holds::<T>() {
+            self.occupant = None;
+            unsafe { Some(ptr::read(self.storage.as_ptr() as *const T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Drops the stored value, if any.
+    pub fn clear(&mut self) {
+        if let Some(occupant) = self.occupant.take() {
+            unsafe { (occupant.drop)(self.storage.as_mut_ptr() as *mut u8) }
+        }
+    }
+}
+
+impl<const S: usize> Default for AnySlot<S> {
+    fn default() -> Self {
+        AnySlot::new()
+    }
+}
+
+impl<const S: usize> Drop for AnySlot<S> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<const S: usize> fmt::Debug for AnySlot<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AnySlot").field("type_id", &self.type_id()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn typed_access() {
+        let mut slot = AnySlot::<16>::new();
+        assert!(slot.is_empty());
+        slot.insert(1u128);
+        assert!(slot.holds::<u128>());
+        *slot.get_mut::<u128>().unwrap() += 1;
+        assert_eq!(slot.take::<u64>(), None);
+        assert_eq!(slot.take::<u128>(), Some(2));
+        assert_eq!(slot.type_id(), None);
+    }
+
+    #[test]
+    fn drops_with_stored_type() {
+        let arc = Arc::new(());
+        let mut slot = AnySlot::<16>::new();
+        slot.insert(arc.clone());
+        slot.insert([0u8; 3]);
+        assert_eq!(Arc::strong_count(&arc), 1);
+        slot.insert(arc.clone());
+        drop(slot);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+}
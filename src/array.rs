@@ -0,0 +1,169 @@
+//! Helpers for arrays of untagged options and untagged options holding arrays.
+
+use core::mem;
+use core::ptr;
+
+use UntaggedOption;
+
+/// Initializes every slot of `arr` with a value returned by `f`.
+///
+/// `f` is called once per slot, in order. Values previously stored in `arr` are overwritten
+/// without being dropped, so `arr` should be empty when calling this.
+///
+/// If `f` panics, the values it returned so far are dropped before the panic propagates, so all
+/// slots of `arr` are left empty.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::UntaggedOption;
+/// # use untagged_option::array::fill_with;
+/// # fn main() {
+/// let mut slots: [UntaggedOption<u32>; 4] = [const { UntaggedOption::none() }; 4];
+/// let mut next = 0;
+/// fill_with(&mut slots, || { next += 1; next });
+///
+/// unsafe {
+///     // Safe: `fill_with` initialized every slot.
+///     assert_eq!(*slots[3].as_ref(), 4);
+/// }
+/// # }
+/// ```
+pub fn fill_with<T, F, const N: usize>(arr: &mut [UntaggedOption<T>; N], mut f: F)
+where
+    F: FnMut() -> T,
+{
+    /// Drops the already initialized prefix of the array if `f` panics.
+    struct Guard<'a, T: 'a> {
+        slots: &'a mut [UntaggedOption<T>],
+        filled: usize,
+    }
+
+    impl<'a, T> Drop for Guard<'a, T> {
+        fn drop(&mut self) {
+            for slot in &mut self.slots[..self.filled] {
+                unsafe { slot.take(); }
+            }
+        }
+    }
+
+    let mut guard = Guard {
+        slots: arr,
+        filled: 0,
+    };
+    while guard.filled < N {
+        guard.slots[guard.filled] = UntaggedOption::some(f());
+        guard.filled += 1;
+    }
+    mem::forget(guard);
+}
+
+impl<T, const N: usize> UntaggedOption<[T; N]> {
+    /// Returns an array of references to the elements of the contained array.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `[T; N]`.
+    pub unsafe fn each_ref(&self) -> [&T; N] {
+        self.as_ref().each_ref()
+    }
+
+    /// Returns an array of mutable references to the elements of the contained array.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `[T; N]`.
+    pub unsafe fn each_mut(&mut self) -> [&mut T; N] {
+        self.as_mut().each_mut()
+    }
+
+    /// Converts an option holding an array into an array of options, one per element.
+    ///
+    /// Since `UntaggedOption<T>` has the same layout as `T`, this is a plain reinterpretation of
+    /// the memory. It never reads the payload and is safe to call in any state: if `self` holds an
+    /// array, every returned option holds the corresponding element.
+    pub fn transpose(self) -> [UntaggedOption<T>; N] {
+        unsafe { ptr::read(&self as *const Self as *const [UntaggedOption<T>; N]) }
+    }
+
+    /// Converts an array of options into an option holding an array.
+    ///
+    /// This is the inverse of [`transpose`]. If every element of `slots` holds a value, the
+    /// returned option holds the array of those values.
+    ///
+    /// [`transpose`]: #method.transpose
+    pub fn from_transposed(slots: [UntaggedOption<T>; N]) -> Self {
+        unsafe { ptr::read(&slots as *const [UntaggedOption<T>; N] as *const Self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl<'a> Drop for DropCounter<'a> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn fills_all_slots() {
+        let mut slots = [const { UntaggedOption::none() }; 3];
+        let mut i = 0;
+        fill_with(&mut slots, || { i += 1; i * 10 });
+        unsafe {
+            assert_eq!(slots.iter().map(|s| *s.as_ref()).sum::<i32>(), 60);
+        }
+    }
+
+    #[test]
+    fn element_references() {
+        let mut opt = UntaggedOption::some([1u8, 2, 3]);
+        unsafe {
+            let [a, b, c] = opt.each_mut();
+            *a += 10;
+            *b += 20;
+            *c += 30;
+            assert_eq!(opt.each_ref(), [&11, &22, &33]);
+        }
+    }
+
+    #[test]
+    fn transpose_roundtrip() {
+        let slots = UntaggedOption::some([1u16, 2, 3, 4]).transpose();
+        unsafe {
+            assert_eq!(*slots[2].as_ref(), 3);
+        }
+
+        let mut slots = slots;
+        fill_with(&mut slots, || 9);
+        let mut opt = UntaggedOption::from_transposed(slots);
+        unsafe {
+            assert_eq!(opt.take(), [9; 4]);
+        }
+    }
+
+    #[test]
+    fn panic_drops_filled_prefix() {
+        let drops = Cell::new(0);
+        let mut slots = [const { UntaggedOption::none() }; 4];
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            fill_with(&mut slots, || {
+                calls += 1;
+                if calls == 3 {
+                    panic!("out of values");
+                }
+                DropCounter(&drops)
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 2);
+    }
+}
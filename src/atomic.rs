@@ -0,0 +1,196 @@
+//! An option that can be filled and emptied from several threads without locks.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use sync;
+use UntaggedOption;
+
+/// The slot is empty.
+const EMPTY: u8 = 0;
+/// A value is being stored by `try_store`.
+const WRITING: u8 = 1;
+/// The slot holds a value.
+const FULL: u8 = 2;
+/// The value is being moved out by `try_take`.
+const TAKING: u8 = 3;
+
+/// An [`UntaggedOption`] with an atomic state, for handing single values between threads or from
+/// interrupt handlers.
+///
+/// The state moves from empty to full through [`try_store`] and back through [`try_take`]. Each
+/// transition first claims the slot with a compare-and-swap, so a store or take that races with
+/// another one fails instead of waiting, and neither ever blocks. This makes it safe to call
+/// either side from an interrupt handler.
+///
+/// A value that is still stored when the option is dropped is dropped with it.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::AtomicUntaggedOption;
+/// # fn main() {
+/// static RESULT: AtomicUntaggedOption<u32> = AtomicUntaggedOption::new();
+///
+/// assert_eq!(RESULT.try_store(42), Ok(()));
+/// assert_eq!(RESULT.try_store(43), Err(43));
+/// assert_eq!(RESULT.try_take(), Some(42));
+/// assert_eq!(RESULT.try_take(), None);
+/// # }
+/// ```
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+/// [`try_store`]: #method.try_store
+/// [`try_take`]: #method.try_take
+pub struct AtomicUntaggedOption<T> {
+    state: AtomicU8,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+unsafe impl<T: Send> Sync for AtomicUntaggedOption<T> {}
+
+impl<T> AtomicUntaggedOption<T> {
+    /// Creates an empty option.
+    pub const fn new() -> Self {
+        AtomicUntaggedOption {
+            state: AtomicU8::new(EMPTY),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Creates an option holding `value`.
+    pub const fn some(value: T) -> Self {
+        AtomicUntaggedOption {
+            state: AtomicU8::new(FULL),
+            slot: UnsafeCell::new(UntaggedOption::some(value)),
+        }
+    }
+
+    /// Returns whether the option currently holds a value.
+    ///
+    /// The answer may be outdated by the time it is returned if other threads access the option.
+    pub fn is_some(&self) -> bool {
+        self.state.load(Ordering::Acquire) == FULL
+    }
+
+    /// Stores `value` if the option is empty.
+    ///
+    /// If the option holds a value, or another thread is storing or taking one, `value` is
+    /// returned as an error.
+    pub fn try_store(&self, value: T) -> Result<(), T> {
+        if !self.transition(EMPTY, WRITING) {
+            return Err(value);
+        }
+        // Safe: claiming the empty slot grants exclusive access to it.
+        unsafe { (*self.slot.get()).write(value) };
+        self.state.store(FULL, Ordering::Release);
+        Ok(())
+    }
+
+    /// Takes the value out of the option if it holds one.
+    ///
+    /// Returns `None` if the option is empty, or another thread is storing or taking a value.
+    pub fn try_take(&self) -> Option<T> {
+        if !self.transition(FULL, TAKING) {
+            return None;
+        }
+        // Safe: claiming the full slot grants exclusive access to the value.
+        let value = unsafe { (*self.slot.get()).take() };
+        self.state.store(EMPTY, Ordering::Release);
+        Some(value)
+    }
+
+    /// Consumes `self` and returns the stored value, if any.
+    pub fn into_inner(mut self) -> Option<T> {
+        if *self.state.get_mut() == FULL {
+            *self.state.get_mut() = EMPTY;
+            unsafe { Some(self.slot.get_mut().take()) }
+        } else {
+            None
+        }
+    }
+
+    fn transition(&self, from: u8, to: u8) -> bool {
+        sync::compare_exchange_u8(&self.state, from, to, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+impl<T> Default for AtomicUntaggedOption<T> {
+    fn default() -> Self {
+        AtomicUntaggedOption::new()
+    }
+}
+
+impl<T> Drop for AtomicUntaggedOption<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == FULL {
+            unsafe { drop(self.slot.get_mut().take()) }
+        }
+    }
+}
+
+impl<T> fmt::Debug for AtomicUntaggedOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AtomicUntaggedOption").field("is_some", &self.is_some()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::AtomicUsize;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn drops_stored_value() {
+        let rc = Rc::new(());
+        let opt = AtomicUntaggedOption::some(rc.clone());
+        assert!(opt.try_store(rc.clone()).is_err());
+        drop(opt);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let opt = AtomicUntaggedOption::new();
+        opt.try_store(rc.clone()).unwrap();
+        assert!(opt.into_inner().is_some());
+    }
+
+    #[test]
+    fn stress() {
+        const PER_PRODUCER: usize = 10_000;
+        let opt = AtomicUntaggedOption::new();
+        let sum = AtomicUsize::new(0);
+        let taken = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for i in 1..=PER_PRODUCER {
+                        let mut value = i;
+                        while let Err(v) = opt.try_store(value) {
+                            value = v;
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+            for _ in 0..2 {
+                s.spawn(|| {
+                    while taken.load(Ordering::Relaxed) < 2 * PER_PRODUCER {
+                        match opt.try_take() {
+                            Some(value) => {
+                                sum.fetch_add(value, Ordering::Relaxed);
+                                taken.fetch_add(1, Ordering::Relaxed);
+                            }
+                            None => thread::yield_now(),
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(sum.into_inner(), PER_PRODUCER * (PER_PRODUCER + 1));
+    }
+}
@@ -0,0 +1,181 @@
+//! A slot that keeps the previous value around for rollback.
+
+use UntaggedOption;
+
+/// Bit set in the state byte when slot `i` holds a value.
+const fn full(i: usize) -> u8 {
+    1 << i
+}
+
+/// Bit set in the state byte when slot 1 holds the current value.
+const CURRENT_IS_1: u8 = 1 << 2;
+
+/// A slot holding a current and a previous value.
+///
+/// [`set`] makes the new value current and keeps the old current value as a backup, dropping the
+/// older backup. [`rollback`] restores the backup, dropping the current value. This fits "revert
+/// to the last known-good configuration" flows.
+///
+/// Both values are stored in two untagged payloads that swap roles when the current value
+/// changes, so values are never moved between them. Their state fits in a single byte.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::BackupSlot;
+/// # fn main() {
+/// let mut config = BackupSlot::new();
+/// config.set("known good");
+/// config.set("experimental");
+/// assert_eq!(config.previous(), Some(&"known good"));
+///
+/// // The experimental configuration failed; go back.
+/// assert!(config.rollback());
+/// assert_eq!(config.current(), Some(&"known good"));
+/// assert_eq!(config.previous(), None);
+/// # }
+/// ```
+///
+/// [`set`]: #method.set
+/// [`rollback`]: #method.rollback
+pub struct BackupSlot<T> {
+    state: u8,
+    slots: [UntaggedOption<T>; 2],
+}
+
+impl<T> BackupSlot<T> {
+    /// Creates a slot with neither a current nor a previous value.
+    pub const fn new() -> Self {
+        BackupSlot {
+            state: 0,
+            slots: [UntaggedOption::none(), UntaggedOption::none()],
+        }
+    }
+
+    /// Returns the index of the slot holding the current value.
+    fn current_index(&self) -> usize {
+        (self.state & CURRENT_IS_1 != 0) as usize
+    }
+
+    /// Returns a reference to the value in slot `i`, if any.
+    fn get(&self, i: usize) -> Option<&T> {
+        if self.state & full(i) != 0 {
+            unsafe { Some(self.slots[i].as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Drops the value in slot `i`, if any.
+    fn clear(&mut self, i: usize) {
+        if self.state & full(i) != 0 {
+            self.state &= !full(i);
+            unsafe { self.slots[i].take(); }
+        }
+    }
+
+    /// Returns a reference to the current value, if any.
+    pub fn current(&self) -> Option<&T> {
+        self.get(self.current_index())
+    }
+
+    /// Returns a mutable reference to the current value, if any.
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        let i = self.current_index();
+        if self.state & full(i) != 0 {
+            unsafe { Some(self.slots[i].as_mut()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the previous value, if any.
+    pub fn previous(&self) -> Option<&T> {
+        self.get(1 - self.current_index())
+    }
+
+    /// Makes `value` the current value.
+    ///
+    /// The old current value becomes the previous value, and the old previous value is dropped.
+    pub fn set(&mut self, value: T) {
+        let next = 1 - self.current_index();
+        self.clear(next);
+        self.slots[next] = UntaggedOption::some(value);
+        self.state = (self.state | full(next)) ^ CURRENT_IS_1;
+    }
+
+    /// Restores the previous value, dropping the current value.
+    ///
+    /// Returns `false` and leaves `self` unchanged if there is no previous value.
+    pub fn rollback(&mut self) -> bool {
+        let current = self.current_index();
+        if self.state & full(1 - current) == 0 {
+            return false;
+        }
+
+        self.clear(current);
+        self.state ^= CURRENT_IS_1;
+        true
+    }
+
+    /// Drops the previous value, for example once the current value is known to be good.
+    pub fn clear_previous(&mut self) {
+        let previous = 1 - self.current_index();
+        self.clear(previous);
+    }
+}
+
+impl<T> Default for BackupSlot<T> {
+    fn default() -> Self {
+        BackupSlot::new()
+    }
+}
+
+impl<T> Drop for BackupSlot<T> {
+    fn drop(&mut self) {
+        self.clear(0);
+        self.clear(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn set_and_rollback() {
+        let mut slot = BackupSlot::new();
+        assert!(!slot.rollback());
+        slot.set(1);
+        assert!(!slot.rollback());
+        slot.set(2);
+        slot.set(3);
+        *slot.current_mut().unwrap() += 10;
+        assert_eq!((slot.current(), slot.previous()), (Some(&13), Some(&2)));
+
+        assert!(slot.rollback());
+        assert_eq!((slot.current(), slot.previous()), (Some(&2), None));
+        assert!(!slot.rollback());
+
+        slot.set(4);
+        slot.clear_previous();
+        assert_eq!((slot.current(), slot.previous()), (Some(&4), None));
+    }
+
+    #[test]
+    fn drops_values() {
+        let rc = Rc::new(());
+        {
+            let mut slot = BackupSlot::new();
+            slot.set(rc.clone());
+            slot.set(rc.clone());
+            slot.set(rc.clone());
+            assert_eq!(Rc::strong_count(&rc), 3);
+            slot.rollback();
+            assert_eq!(Rc::strong_count(&rc), 2);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
@@ -0,0 +1,355 @@
+//! Packed presence flags.
+
+use core::fmt;
+use core::slice;
+
+use error::IndexOutOfBounds;
+use UntaggedOption;
+
+/// Number of bits stored in one word of an [`OptionBitmap`].
+///
+/// [`OptionBitmap`]: crate::OptionBitmap
+pub const WORD_BITS: usize = 32;
+
+/// Returns the number of bitmap words needed to track `slots` slots.
+///
+/// This can be used to compute the word count parameter of [`OptionBitmap`] and
+/// [`OptionGroup`] for groups larger than 32 slots:
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::{bitmap_words, OptionGroup};
+/// # fn main() {
+/// let group: OptionGroup<u16, 100, { bitmap_words(100) }> = OptionGroup::new();
+/// assert_eq!(group.capacity(), 100);
+/// # }
+/// ```
+///
+/// [`OptionBitmap`]: crate::OptionBitmap
+/// [`OptionGroup`]: crate::OptionGroup
+pub const fn bitmap_words(slots: usize) -> usize {
+    slots.div_ceil(WORD_BITS)
+}
+
+/// A set of presence flags, packed one bit per slot into `W` 32-bit words.
+///
+/// The bitmap can track up to `32 * W` slots. The default of one word covers 32 slots.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OptionBitmap<const W: usize = 1> {
+    words: [u32; W],
+}
+
+impl<const W: usize> OptionBitmap<W> {
+    /// The number of flags the bitmap can hold.
+    pub const CAPACITY: usize = W * WORD_BITS;
+
+    /// Creates a bitmap with all flags cleared.
+    pub const fn new() -> Self {
+        OptionBitmap {
+            words: [0; W],
+        }
+    }
+
+    /// Returns the flag at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn get(&self, index: usize) -> bool {
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Sets the flag at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn set(&mut self, index: usize) {
+        self.words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+    }
+
+    /// Clears the flag at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn clear(&mut self, index: usize) {
+        self.words[index / WORD_BITS] &= !(1 << (index % WORD_BITS));
+    }
+
+    /// Returns the flag at `index`, or an error if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn try_get(&self, index: usize) -> Result<bool, IndexOutOfBounds> {
+        self.check(index)?;
+        Ok(self.get(index))
+    }
+
+    /// Sets the flag at `index`, or returns an error if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn try_set(&mut self, index: usize) -> Result<(), IndexOutOfBounds> {
+        self.check(index)?;
+        self.set(index);
+        Ok(())
+    }
+
+    /// Clears the flag at `index`, or returns an error if `index` is not less than [`CAPACITY`].
+    ///
+    /// [`CAPACITY`]: #associatedconstant.CAPACITY
+    pub fn try_clear(&mut self, index: usize) -> Result<(), IndexOutOfBounds> {
+        self.check(index)?;
+        self.clear(index);
+        Ok(())
+    }
+
+    fn check(&self, index: usize) -> Result<(), IndexOutOfBounds> {
+        if index < Self::CAPACITY {
+            Ok(())
+        } else {
+            Err(IndexOutOfBounds {
+                index,
+                len: Self::CAPACITY,
+            })
+        }
+    }
+
+    /// Clears all flags.
+    pub fn clear_all(&mut self) {
+        self.words = [0; W];
+    }
+
+    /// Returns the number of set flags.
+    ///
+    /// This counts one word at a time instead of testing every flag individually.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns whether no flag is set.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the index of the first set flag, if any.
+    pub fn first_set(&self) -> Option<usize> {
+        self.words.iter().enumerate().find(|&(_, &word)| word != 0).map(|(i, word)| {
+            i * WORD_BITS + word.trailing_zeros() as usize
+        })
+    }
+
+    /// Returns the index of the last set flag, if any.
+    pub fn last_set(&self) -> Option<usize> {
+        self.words.iter().enumerate().rev().find(|&(_, &word)| word != 0).map(|(i, word)| {
+            i * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize)
+        })
+    }
+
+    /// Returns the index of the first cleared flag, if any.
+    pub fn first_clear(&self) -> Option<usize> {
+        self.words.iter().enumerate().find(|&(_, &word)| word != !0).map(|(i, word)| {
+            i * WORD_BITS + word.trailing_ones() as usize
+        })
+    }
+
+    /// Returns an iterator over the indices of all set flags, in ascending order.
+    ///
+    /// Empty words are skipped as a whole, so iterating a sparse bitmap takes time proportional
+    /// to the number of words plus the number of set flags.
+    pub fn iter_set(&self) -> SetBits<'_> {
+        SetBits {
+            words: self.words.iter(),
+            base: 0,
+            current: 0,
+        }
+    }
+
+    /// Writes a summary of the bitmap to `out`, for diagnostics.
+    ///
+    /// This prints the number of set flags, the capacity and one character per flag: `x` for a
+    /// set flag and `.` for a cleared one, starting with flag 0.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "OptionBitmap count={} capacity={} flags=", self.count(), Self::CAPACITY)?;
+        write_flags(out, (0..Self::CAPACITY).map(|i| self.get(i)))
+    }
+
+    /// Returns the underlying words.
+    ///
+    /// Flag `i` is stored in bit `i % 32` of word `i / 32`.
+    pub fn words(&self) -> &[u32; W] {
+        &self.words
+    }
+}
+
+impl<const W: usize> Default for OptionBitmap<W> {
+    fn default() -> Self {
+        OptionBitmap::new()
+    }
+}
+
+/// Writes one character per flag to `out`: `x` for a set flag and `.` for a cleared one.
+pub(crate) fn write_flags<I>(out: &mut dyn fmt::Write, flags: I) -> fmt::Result
+where
+    I: IntoIterator<Item = bool>,
+{
+    for flag in flags {
+        out.write_char(if flag { 'x' } else { '.' })?;
+    }
+    Ok(())
+}
+
+/// Takes every value marked present in `bitmap` out of `slots`, clearing the bitmap.
+///
+/// `f` is called with the index and value of each initialized slot, in ascending order. Empty
+/// words of the bitmap are skipped as a whole, so this runs in a single pass that is proportional
+/// to the number of words plus the number of present values. This is useful for tearing down
+/// containers, for example in a `Drop` implementation.
+///
+/// Each flag is cleared before its value is passed to `f`. If `f` panics, the remaining values are
+/// left in place and their flags stay set.
+///
+/// # Safety
+///
+/// For every flag `i` set in `bitmap`, `slots[i]` must hold a valid `T`.
+pub unsafe fn take_all<T, F, const W: usize>(
+    slots: &mut [UntaggedOption<T>],
+    bitmap: &mut OptionBitmap<W>,
+    mut f: F,
+) where
+    F: FnMut(usize, T),
+{
+    for w in 0..W {
+        while bitmap.words[w] != 0 {
+            let word = bitmap.words[w];
+            let index = w * WORD_BITS + word.trailing_zeros() as usize;
+            bitmap.words[w] = word & (word - 1);
+            f(index, slots[index].take());
+        }
+    }
+}
+
+/// Iterator over the indices of the set flags in an [`OptionBitmap`].
+///
+/// Returned by [`OptionBitmap::iter_set`].
+///
+/// [`OptionBitmap`]: crate::OptionBitmap
+/// [`OptionBitmap::iter_set`]: crate::OptionBitmap::iter_set
+pub struct SetBits<'a> {
+    words: slice::Iter<'a, u32>,
+    /// Index of the first flag in `current`.
+    base: usize,
+    /// Remaining set bits of the word currently being scanned.
+    current: u32,
+}
+
+impl<'a> Iterator for SetBits<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.current = *self.words.next()?;
+            self.base += WORD_BITS;
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.base - WORD_BITS + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[test]
+    fn set_and_clear() {
+        let mut bitmap = OptionBitmap::<2>::new();
+        bitmap.set(0);
+        bitmap.set(33);
+        assert!(bitmap.get(0));
+        assert!(!bitmap.get(1));
+        assert!(bitmap.get(33));
+        assert_eq!(bitmap.words(), &[0b1, 0b10]);
+
+        bitmap.clear(0);
+        assert!(!bitmap.get(0));
+        assert_eq!(bitmap.try_get(33), Ok(true));
+        assert_eq!(bitmap.try_set(64), Err(IndexOutOfBounds { index: 64, len: 64 }));
+        assert_eq!(bitmap.try_clear(33), Ok(()));
+        bitmap.clear_all();
+        assert_eq!(bitmap, OptionBitmap::new());
+    }
+
+    #[test]
+    fn bulk_queries() {
+        let mut bitmap = OptionBitmap::<3>::new();
+        assert!(bitmap.is_empty());
+        assert_eq!(bitmap.first_set(), None);
+        assert_eq!(bitmap.last_set(), None);
+        assert_eq!(bitmap.first_clear(), Some(0));
+
+        for i in 0..40 {
+            bitmap.set(i);
+        }
+        bitmap.set(95);
+        bitmap.clear(3);
+        assert_eq!(bitmap.count(), 40);
+        assert_eq!(bitmap.first_set(), Some(0));
+        assert_eq!(bitmap.last_set(), Some(95));
+        assert_eq!(bitmap.first_clear(), Some(3));
+        bitmap.set(3);
+        assert_eq!(bitmap.first_clear(), Some(40));
+
+        let indices: Vec<_> = bitmap.iter_set().collect();
+        let expected: Vec<_> = (0..40).chain(Some(95)).collect();
+        assert_eq!(indices, expected);
+
+        for i in 0..96 {
+            bitmap.set(i);
+        }
+        assert_eq!(bitmap.first_clear(), None);
+    }
+
+    #[test]
+    fn take_all_drains_present_slots() {
+        let mut slots = [const { UntaggedOption::none() }; 40];
+        let mut bitmap = OptionBitmap::<2>::new();
+        for &i in &[2, 31, 39] {
+            slots[i] = UntaggedOption::some(i * 10);
+            bitmap.set(i);
+        }
+
+        let mut taken = Vec::new();
+        unsafe {
+            take_all(&mut slots, &mut bitmap, |i, value| taken.push((i, value)));
+        }
+        assert_eq!(taken, [(2, 20), (31, 310), (39, 390)]);
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn dump() {
+        let mut bitmap = OptionBitmap::<1>::new();
+        bitmap.set(1);
+        bitmap.set(31);
+        let mut out = String::new();
+        bitmap.dump(&mut out).unwrap();
+        assert_eq!(out, "OptionBitmap count=2 capacity=32 flags=.x.............................x");
+    }
+
+    #[test]
+    fn word_count() {
+        assert_eq!(bitmap_words(0), 0);
+        assert_eq!(bitmap_words(1), 1);
+        assert_eq!(bitmap_words(32), 1);
+        assert_eq!(bitmap_words(33), 2);
+    }
+}
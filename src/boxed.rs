@@ -0,0 +1,146 @@
+//! Helpers for boxed payloads.
+
+use alloc::boxed::Box;
+use core::mem::MaybeUninit;
+
+use poison;
+use UntaggedOption;
+
+impl<T: ?Sized> UntaggedOption<Box<T>> {
+    /// Obtains an immutable reference to the boxed value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `Box<T>`.
+    pub unsafe fn as_deref(&self) -> &T {
+        self.as_ref()
+    }
+
+    /// Obtains a mutable reference to the boxed value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `Box<T>`.
+    pub unsafe fn as_deref_mut(&mut self) -> &mut T {
+        self.as_mut()
+    }
+
+    /// Converts `self` into an `Option<Box<T>>`, which is pointer-sized as well.
+    ///
+    /// This is the inverse of [`from_option_box`].
+    ///
+    /// # Safety
+    ///
+    /// `is_some` must be `true` if and only if `self` holds a valid `Box<T>`.
+    ///
+    /// [`from_option_box`]: #method.from_option_box
+    pub unsafe fn into_option_box(self, is_some: bool) -> Option<Box<T>> {
+        if is_some {
+            Some(self.into_inner())
+        } else {
+            None
+        }
+    }
+
+    /// Converts an `Option<Box<T>>` into an option and a flag telling whether it holds the box.
+    ///
+    /// If the flag is `false`, the returned option is empty.
+    pub fn from_option_box(opt: Option<Box<T>>) -> (Self, bool) {
+        match opt {
+            Some(boxed) => (UntaggedOption::some(boxed), true),
+            None => (UntaggedOption::none(), false),
+        }
+    }
+}
+
+impl<T> UntaggedOption<T> {
+    /// Allocates an empty `UntaggedOption<T>` on the heap.
+    ///
+    /// The payload is not initialized, so no `T` is moved to the heap. Together with
+    /// [`assume_init_box`] this allows initializing a large payload in place.
+    ///
+    /// With the `poison` feature, the storage is filled with the poison pattern, like with
+    /// [`none`].
+    ///
+    /// [`assume_init_box`]: #method.assume_init_box
+    /// [`none`]: #method.none
+    pub fn none_boxed() -> Box<Self> {
+        let uninit: Box<MaybeUninit<T>> = Box::new_uninit();
+        // Safe: both types have the same layout, and an empty `UntaggedOption` is uninitialized.
+        let mut boxed = unsafe { Box::from_raw(Box::into_raw(uninit) as *mut Self) };
+        if Self::is_poisoning() {
+            poison::poison(&mut boxed);
+        }
+        boxed
+    }
+
+    /// Converts a boxed `UntaggedOption<T>` into a `Box<T>`, without moving the payload.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `boxed` holds a valid `T`.
+    pub unsafe fn assume_init_box(boxed: Box<Self>) -> Box<T> {
+        Box::from_raw(Box::into_raw(boxed) as *mut T)
+    }
+
+    /// Converts a `Box<T>` into a boxed `UntaggedOption<T>` holding the value, without moving it.
+    ///
+    /// The value is no longer dropped automatically once it is in the `UntaggedOption`.
+    pub fn from_box(boxed: Box<T>) -> Box<Self> {
+        // Safe: `UntaggedOption<T>` has the same layout as `T`.
+        unsafe { Box::from_raw(Box::into_raw(boxed) as *mut Self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deref_boxed() {
+        let mut opt = UntaggedOption::some(Box::new([0u8; 64]) as Box<[u8]>);
+        unsafe {
+            opt.as_deref_mut()[63] = 1;
+            assert_eq!(opt.as_deref().len(), 64);
+            assert_eq!(opt.as_deref()[63], 1);
+            opt.take();
+        }
+    }
+
+    #[test]
+    fn option_box_conversions() {
+        let (opt, is_some) = UntaggedOption::from_option_box(Some(Box::new(3u8) as Box<dyn Send>));
+        assert!(is_some);
+        assert!(unsafe { opt.into_option_box(is_some) }.is_some());
+
+        let (opt, is_some) = UntaggedOption::<Box<str>>::from_option_box(None);
+        assert_eq!(unsafe { opt.into_option_box(is_some) }, None);
+    }
+
+    #[test]
+    fn box_conversions() {
+        let mut boxed = UntaggedOption::<[u64; 512]>::none_boxed();
+        unsafe {
+            let ptr = boxed.as_mut_ptr();
+            for i in 0..512 {
+                (*ptr)[i] = i as u64;
+            }
+            let array = UntaggedOption::assume_init_box(boxed);
+            assert_eq!(array[511], 511);
+
+            let addr = &*array as *const [u64; 512] as usize;
+            let mut boxed = UntaggedOption::from_box(array);
+            assert_eq!(boxed.as_ptr() as usize, addr);
+            boxed.take();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "poison")]
+    fn boxed_none_is_poisoned() {
+        use poison::PoisonDetected;
+
+        let boxed = UntaggedOption::<[u32; 16]>::none_boxed();
+        assert_eq!(unsafe { boxed.validate() }, Err(PoisonDetected));
+    }
+}
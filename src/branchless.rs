@@ -0,0 +1,99 @@
+//! Branchless accessors for slots whose presence is tracked by a separate flag.
+//!
+//! The functions in this module always read the slot and only then pick either its contents or a
+//! fallback based on the flag. This lets the compiler emit a conditional move instead of a branch,
+//! which pays off in hot loops over sparsely populated slots, where a branch on the flag would be
+//! mispredicted often.
+//!
+//! Reading an empty slot is fine here: the payload is only ever read as possibly-uninitialized
+//! memory, and is discarded unless the flag says it is valid.
+
+use core::hint::select_unpredictable;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use UntaggedOption;
+
+/// Returns a copy of the value in `slot` if `flag` is set, and `default` otherwise.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::UntaggedOption;
+/// # use untagged_option::branchless::select;
+/// # fn main() {
+/// let slots = [UntaggedOption::some(4), UntaggedOption::none(), UntaggedOption::some(2)];
+/// let flags = [true, false, true];
+///
+/// let sum: u32 = flags.iter().zip(&slots).map(|(&flag, slot)| unsafe {
+///     // Safe: every slot whose flag is set holds a value.
+///     select(flag, slot, 0)
+/// }).sum();
+/// assert_eq!(sum, 6);
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// If `flag` is `true`, `slot` must hold a valid `T`.
+pub unsafe fn select<T: Copy>(flag: bool, slot: &UntaggedOption<T>, default: T) -> T {
+    let value = ptr::read(slot.as_ptr() as *const MaybeUninit<T>);
+    select_unpredictable(flag, value, MaybeUninit::new(default)).assume_init()
+}
+
+/// Returns a reference to the value in `slot` if `flag` is set, and `default` otherwise.
+///
+/// Only the address is selected, so this also works for payloads that are not `Copy`.
+///
+/// # Safety
+///
+/// If `flag` is `true`, `slot` must hold a valid `T`.
+pub unsafe fn select_ref<'a, T>(flag: bool, slot: &'a UntaggedOption<T>, default: &'a T) -> &'a T {
+    &*select_unpredictable(flag, slot.as_ptr(), default as *const T)
+}
+
+/// Returns a mutable reference to the value in `slot` if `flag` is set, and `default` otherwise.
+///
+/// # Safety
+///
+/// If `flag` is `true`, `slot` must hold a valid `T`.
+pub unsafe fn select_mut<'a, T>(
+    flag: bool,
+    slot: &'a mut UntaggedOption<T>,
+    default: &'a mut T,
+) -> &'a mut T {
+    &mut *select_unpredictable(flag, slot.as_mut_ptr(), default as *mut T)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_by_flag() {
+        let full = UntaggedOption::some(7u64);
+        let empty = UntaggedOption::<u64>::none();
+        unsafe {
+            assert_eq!(select(true, &full, 0), 7);
+            assert_eq!(select(false, &full, 0), 0);
+            assert_eq!(select(false, &empty, 3), 3);
+        }
+    }
+
+    #[test]
+    fn selects_references() {
+        let mut full = UntaggedOption::some([1u8; 16]);
+        let mut empty = UntaggedOption::none();
+        let mut fallback = [0u8; 16];
+        unsafe {
+            assert_eq!(select_ref(true, &full, &fallback), &[1; 16]);
+            assert_eq!(select_ref(false, &empty, &fallback), &[0; 16]);
+
+            select_mut(true, &mut full, &mut fallback)[0] = 2;
+            select_mut(false, &mut empty, &mut fallback)[0] = 3;
+            assert_eq!(full.as_ref()[0], 2);
+        }
+        assert_eq!(fallback[0], 3);
+    }
+}
@@ -0,0 +1,194 @@
+//! One-to-many distribution of `Copy` values.
+
+use no_padding::NoPadding;
+use versioned::VersionedSlot;
+
+/// A slot that publishes `Copy` values to any number of readers.
+///
+/// Each published value gets a new generation number from the underlying [`VersionedSlot`].
+/// Readers obtain a [`Receiver`] through [`subscribe`], which remembers the generation it last
+/// saw, so [`Receiver::recv`] only returns a value when a newer one has been published since.
+/// Readers that fall behind skip straight to the latest value.
+///
+/// Publishing never waits for readers, and reading never blocks the writer.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::Broadcast;
+/// # fn main() {
+/// static SETPOINT: Broadcast<f32> = Broadcast::new();
+///
+/// let mut rx = SETPOINT.subscribe();
+/// assert_eq!(rx.recv(), None);
+///
+/// SETPOINT.publish(20.5);
+/// SETPOINT.publish(21.0);
+/// assert_eq!(rx.recv(), Some(21.0));
+/// assert_eq!(rx.recv(), None);
+/// # }
+/// ```
+///
+/// [`VersionedSlot`]: crate::VersionedSlot
+/// [`Receiver`]: crate::broadcast::Receiver
+/// [`subscribe`]: #method.subscribe
+/// [`Receiver::recv`]: crate::broadcast::Receiver::recv
+pub struct Broadcast<T: Copy + NoPadding> {
+    slot: VersionedSlot<T>,
+}
+
+impl<T: Copy + NoPadding> Broadcast<T> {
+    /// Creates a broadcast slot that has not published anything yet.
+    pub const fn new() -> Self {
+        Broadcast {
+            slot: VersionedSlot::new(),
+        }
+    }
+
+    /// Publishes `value` to all receivers, returning its generation.
+    ///
+    /// If another thread is publishing at the same time, this waits for it to finish.
+    pub fn publish(&self, value: T) -> usize {
+        self.slot.write(value)
+    }
+
+    /// Returns the generation of the most recently published value, or 0 if nothing was
+    /// published yet.
+    pub fn generation(&self) -> usize {
+        self.slot.version()
+    }
+
+    /// Returns a copy of the most recently published value, if any.
+    pub fn latest(&self) -> Option<T> {
+        self.slot.read()
+    }
+
+    /// Creates a receiver that has not seen any value yet.
+    ///
+    /// Its first call to [`recv`] returns the latest value, if one was published already.
+    ///
+    /// [`recv`]: crate::broadcast::Receiver::recv
+    pub fn subscribe(&self) -> Receiver<'_, T> {
+        Receiver {
+            broadcast: self,
+            last_seen: 0,
+        }
+    }
+
+    /// Creates a receiver that has already seen the current value.
+    ///
+    /// It only receives values that are published after this call.
+    pub fn subscribe_new(&self) -> Receiver<'_, T> {
+        Receiver {
+            broadcast: self,
+            last_seen: self.generation(),
+        }
+    }
+}
+
+impl<T: Copy + NoPadding> Default for Broadcast<T> {
+    fn default() -> Self {
+        Broadcast::new()
+    }
+}
+
+/// A reader handle of a [`Broadcast`] slot.
+///
+/// [`Broadcast`]: crate::Broadcast
+pub struct Receiver<'a, T: Copy + NoPadding + 'a> {
+    broadcast: &'a Broadcast<T>,
+    last_seen: usize,
+}
+
+impl<'a, T: Copy + NoPadding> Receiver<'a, T> {
+    /// Returns a copy of the latest value if it is newer than the last one this receiver saw.
+    pub fn recv(&mut self) -> Option<T> {
+        self.recv_versioned().map(|(value, _)| value)
+    }
+
+    /// Like [`recv`], but also returns the generation of the value.
+    ///
+    /// Comparing it with [`last_seen`] before the call tells how many values were skipped.
+    ///
+    /// [`recv`]: #method.recv
+    /// [`last_seen`]: #method.last_seen
+    pub fn recv_versioned(&mut self) -> Option<(T, usize)> {
+        if !self.has_changed() {
+            return None;
+        }
+        let (value, generation) = self.broadcast.slot.read_versioned()?;
+        self.last_seen = generation;
+        Some((value, generation))
+    }
+
+    /// Returns whether a value newer than the last one this receiver saw has been published.
+    pub fn has_changed(&self) -> bool {
+        self.broadcast.generation() > self.last_seen
+    }
+
+    /// Returns the generation of the last value this receiver saw, or 0 if it saw none.
+    pub fn last_seen(&self) -> usize {
+        self.last_seen
+    }
+}
+
+impl<'a, T: Copy + NoPadding> Clone for Receiver<'a, T> {
+    fn clone(&self) -> Self {
+        Receiver {
+            broadcast: self.broadcast,
+            last_seen: self.last_seen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn receivers_track_generations() {
+        let broadcast = Broadcast::new();
+        broadcast.publish(1u32);
+
+        let mut old = broadcast.subscribe();
+        let mut new = broadcast.subscribe_new();
+        assert!(!new.has_changed());
+        assert_eq!(old.recv_versioned(), Some((1, 1)));
+        assert_eq!(old.recv(), None);
+        assert_eq!(new.recv(), None);
+
+        broadcast.publish(2);
+        broadcast.publish(3);
+        let mut copy = old.clone();
+        assert_eq!(old.recv_versioned(), Some((3, 3)));
+        assert_eq!(new.recv(), Some(3));
+        assert_eq!(copy.last_seen(), 1);
+        assert_eq!(copy.recv(), Some(3));
+    }
+
+    #[test]
+    fn readers_see_monotonic_values() {
+        let broadcast = Broadcast::new();
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=10_000u64 {
+                    broadcast.publish(i);
+                }
+            });
+            for _ in 0..2 {
+                s.spawn(|| {
+                    let mut rx = broadcast.subscribe();
+                    let mut last = 0;
+                    while last < 10_000 {
+                        if let Some(value) = rx.recv() {
+                            assert!(value > last);
+                            last = value;
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
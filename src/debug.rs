@@ -0,0 +1,123 @@
+//! Formatting adapters for logging the contents of options.
+//!
+//! `UntaggedOption` does not implement `Debug`, since it cannot know whether it holds a value.
+//! When the caller knows, [`UntaggedOption::debug`] returns an adapter that formats the value in
+//! place. [`UntaggedOption::raw_bytes`] formats the storage as hex bytes instead, for inspecting
+//! options whose value is in doubt but whose storage is known to be initialized.
+//! [`UntaggedOption::as_uninit_bytes`] gives safe access to the storage as possibly uninitialized
+//! bytes.
+//!
+//! With the `defmt` feature, both adapters also implement `defmt::Format`, so they can be logged
+//! over RTT without copying the value out first.
+//!
+//! [`UntaggedOption::debug`]: crate::UntaggedOption::debug
+//! [`UntaggedOption::raw_bytes`]: crate::UntaggedOption::raw_bytes
+//! [`UntaggedOption::as_uninit_bytes`]: crate::UntaggedOption::as_uninit_bytes
+
+use core::fmt;
+use core::mem::{self, MaybeUninit};
+use core::slice;
+
+#[cfg(feature = "defmt")]
+use defmt;
+
+use policy::Policy;
+use UntaggedOption;
+
+/// Formats the value of an option that is known to hold one, see [`UntaggedOption::debug`].
+///
+/// [`UntaggedOption::debug`]: crate::UntaggedOption::debug
+pub struct AssumeSome<'a, T: 'a>(&'a T);
+
+/// Formats the storage of an option as bytes, see [`UntaggedOption::raw_bytes`].
+///
+/// [`UntaggedOption::raw_bytes`]: crate::UntaggedOption::raw_bytes
+pub struct RawBytes<'a>(&'a [u8]);
+
+impl<T, P: Policy> UntaggedOption<T, P> {
+    /// Returns an adapter that formats the contained `T` with its `Debug` impl.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn debug(&self) -> AssumeSome<'_, T> {
+        AssumeSome(self.as_ref())
+    }
+
+    /// Returns the storage of `self` as bytes that may be uninitialized.
+    ///
+    /// This is safe in any state, since a `MaybeUninit<u8>` does not have to be initialized.
+    pub fn as_uninit_bytes(&self) -> &[MaybeUninit<u8>] {
+        let len = mem::size_of::<T>();
+        unsafe { slice::from_raw_parts(self.as_ptr() as *const MaybeUninit<u8>, len) }
+    }
+
+    /// Returns an adapter that formats the storage of `self` as hex bytes.
+    ///
+    /// # Safety
+    ///
+    /// Every byte of the storage is read, and reading an uninitialized byte is undefined behavior
+    /// even if it is only printed. So every byte must be initialized: either `self` holds a valid
+    /// `T` of a type without padding bytes, or all of its storage was written, for example with
+    /// the poison pattern or by zeroing it. Options that were never written to, and options
+    /// holding a `T` with padding, do not qualify; use [`as_uninit_bytes`] for those.
+    ///
+    /// [`as_uninit_bytes`]: #method.as_uninit_bytes
+    pub unsafe fn raw_bytes(&self) -> RawBytes<'_> {
+        RawBytes(slice::from_raw_parts(self.as_ptr() as *const u8, mem::size_of::<T>()))
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for AssumeSome<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a> fmt::Debug for RawBytes<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        f.write_str("]")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a, T: defmt::Format> defmt::Format for AssumeSome<'a, T> {
+    fn format(&self, f: defmt::Formatter) {
+        self.0.format(f)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for RawBytes<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=[u8]:02x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::format;
+
+    #[test]
+    fn adapters() {
+        let opt = UntaggedOption::some((1u8, 'x'));
+        assert_eq!(format!("{:?}", unsafe { opt.debug() }), "(1, 'x')");
+
+        let opt = UntaggedOption::some(0x0102u16.to_be());
+        assert_eq!(format!("{:?}", unsafe { opt.raw_bytes() }), "[01 02]");
+
+        let opt = UntaggedOption::<(u8, u16)>::none();
+        assert_eq!(opt.as_uninit_bytes().len(), 4);
+    }
+}
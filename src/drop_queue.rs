@@ -0,0 +1,154 @@
+//! Deferred destruction of values.
+
+use core::fmt;
+
+use UntaggedOption;
+
+/// A fixed-capacity queue of values whose destructors run later.
+///
+/// Dropping some values is too expensive (or not allowed) in certain contexts, such as interrupt
+/// handlers or critical sections. Such values can be moved into a `DropQueue` with [`defer`],
+/// which only stores them, and dropped later from a better suited context with [`flush`].
+///
+/// Any values still queued when the `DropQueue` itself is dropped are dropped with it.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::DropQueue;
+/// # fn main() {
+/// let mut queue = DropQueue::<Vec<u8>, 4>::new();
+///
+/// // In the interrupt handler: get rid of the buffer without freeing it.
+/// queue.defer(vec![0; 512]).unwrap();
+///
+/// // Later, in the main loop:
+/// queue.flush();
+/// assert!(queue.is_empty());
+/// # }
+/// ```
+///
+/// [`defer`]: #method.defer
+/// [`flush`]: #method.flush
+pub struct DropQueue<T, const N: usize> {
+    len: usize,
+    slots: [UntaggedOption<T>; N],
+}
+
+impl<T, const N: usize> DropQueue<T, N> {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        DropQueue {
+            len: 0,
+            slots: [const { UntaggedOption::none() }; N],
+        }
+    }
+
+    /// Returns the number of queued values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no values are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Returns the maximum number of values the queue can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Writes the length and capacity of the queue to `out`, for diagnostics.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "DropQueue len={} capacity={}", self.len, N)
+    }
+
+    /// Queues `value` to be dropped by the next call to [`flush`].
+    ///
+    /// This never runs any destructor. If the queue is full, `value` is handed back as an error.
+    ///
+    /// [`flush`]: #method.flush
+    pub fn defer(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+
+        self.slots[self.len] = UntaggedOption::some(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Drops all queued values, in the order they were queued.
+    ///
+    /// If a destructor panics, the values queued after it are leaked.
+    pub fn flush(&mut self) {
+        let len = self.len;
+        self.len = 0;
+        for slot in &mut self.slots[..len] {
+            unsafe { slot.take(); }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for DropQueue<T, N> {
+    fn default() -> Self {
+        DropQueue::new()
+    }
+}
+
+impl<T, const N: usize> Drop for DropQueue<T, N> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use std::vec::Vec;
+
+    struct Logged<'a>(u8, &'a RefCell<Vec<u8>>);
+
+    impl<'a> Drop for Logged<'a> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn defers_drops_until_flush() {
+        let log = RefCell::new(Vec::new());
+        let mut queue = DropQueue::<_, 2>::new();
+        assert!(queue.defer(Logged(1, &log)).is_ok());
+        assert!(queue.defer(Logged(2, &log)).is_ok());
+        assert!(queue.is_full());
+
+        let rejected = queue.defer(Logged(3, &log)).err().unwrap();
+        assert_eq!(rejected.0, 3);
+        drop(rejected);
+        assert_eq!(*log.borrow(), [3]);
+
+        queue.flush();
+        assert_eq!(*log.borrow(), [3, 1, 2]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drop_flushes() {
+        let log = RefCell::new(Vec::new());
+        {
+            let mut queue = DropQueue::<_, 4>::new();
+            assert!(queue.defer(Logged(7, &log)).is_ok());
+            assert_eq!(queue.len(), 1);
+        }
+        assert_eq!(*log.borrow(), [7]);
+    }
+}
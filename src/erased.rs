@@ -0,0 +1,109 @@
+//! Type-erased access to slots.
+
+use core::mem;
+
+use flagged::Flagged;
+use policy::Policy;
+use UntaggedOption;
+
+/// Type-erased access to a slot holding a value of some type.
+///
+/// The trait is object safe, so slots of different types can be handled through
+/// `&mut dyn ErasedSlot`, for example to tear down all slots in the [`registry`] during shutdown.
+///
+/// [`registry`]: crate::registry
+pub trait ErasedSlot {
+    /// Returns the size of the payload type in bytes.
+    fn size(&self) -> usize;
+
+    /// Returns the alignment of the payload type in bytes.
+    fn align(&self) -> usize;
+
+    /// Returns whether the slot holds a value, if the slot can tell.
+    ///
+    /// Slots that track their contents return `Some`, slots that don't (such as an
+    /// `UntaggedOption` with a policy other than [`Checked`]) return `None`. Slots whose contents
+    /// are modified without going through their methods may report stale information, hence the
+    /// name.
+    ///
+    /// [`Checked`]: crate::policy::Checked
+    fn is_probably_init(&self) -> Option<bool>;
+
+    /// Drops the value held by the slot in place and leaves the slot empty.
+    ///
+    /// # Safety
+    ///
+    /// Slots that do not know whether they hold a value must hold a valid one. Slots that track
+    /// their contents ignore this call if they are empty.
+    unsafe fn drop_in_place(&mut self);
+}
+
+impl<T, P: Policy> ErasedSlot for UntaggedOption<T, P> {
+    fn size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    fn align(&self) -> usize {
+        mem::align_of::<T>()
+    }
+
+    fn is_probably_init(&self) -> Option<bool> {
+        self.tag()
+    }
+
+    unsafe fn drop_in_place(&mut self) {
+        if self.is_probably_init() != Some(false) {
+            UntaggedOption::drop_in_place(self);
+        }
+    }
+}
+
+impl<T> ErasedSlot for Flagged<T> {
+    fn size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+
+    fn align(&self) -> usize {
+        mem::align_of::<T>()
+    }
+
+    fn is_probably_init(&self) -> Option<bool> {
+        Some(self.is_some())
+    }
+
+    unsafe fn drop_in_place(&mut self) {
+        self.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use policy::Checked;
+    use std::rc::Rc;
+
+    #[test]
+    fn tear_down_mixed_slots() {
+        let rc = Rc::new(());
+        let mut raw = UntaggedOption::some(rc.clone());
+        let mut checked = UntaggedOption::<_, Checked>::some_with_policy(rc.clone());
+        let mut flagged = Flagged::some([rc.clone(), rc.clone()]);
+        assert_eq!(Rc::strong_count(&rc), 5);
+
+        {
+            let slots: [&mut dyn ErasedSlot; 3] = [&mut raw, &mut checked, &mut flagged];
+            assert_eq!(slots[0].is_probably_init(), None);
+            assert_eq!(slots[1].is_probably_init(), Some(true));
+            assert_eq!(slots[2].size(), 2 * mem::size_of::<Rc<()>>());
+            for slot in slots {
+                unsafe { slot.drop_in_place() };
+                assert_ne!(slot.is_probably_init(), Some(true));
+                // Empty slots that track their contents ignore repeated teardown.
+                if slot.is_probably_init().is_some() {
+                    unsafe { slot.drop_in_place() };
+                }
+            }
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Error types returned by the fallible methods of the containers.
+//!
+//! All of them implement [`Display`] and [`core::error::Error`], so they can be propagated with
+//! `?` into application error types.
+//!
+//! [`Display`]: core::fmt::Display
+
+use core::error::Error;
+use core::fmt;
+
+/// A container was full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityExceeded {
+    /// The capacity of the container.
+    pub capacity: usize,
+}
+
+impl fmt::Display for CapacityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "capacity of {} exceeded", self.capacity)
+    }
+}
+
+impl Error for CapacityExceeded {}
+
+/// An index was not less than the length of the container it was used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOutOfBounds {
+    /// The index that was used.
+    pub index: usize,
+    /// The length of the container.
+    pub len: usize,
+}
+
+/// A value could not be inserted because its index was out of bounds.
+///
+/// The value is handed back, so it is not lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsertError<T> {
+    /// The index that was used.
+    pub index: usize,
+    /// The length of the container.
+    pub len: usize,
+    /// The value that was to be inserted.
+    pub value: T,
+}
+
+impl fmt::Display for IndexOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "index {} out of bounds for length {}", self.index, self.len)
+    }
+}
+
+impl Error for IndexOutOfBounds {}
+
+impl<T> InsertError<T> {
+    /// Returns the value that was to be inserted.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Display for InsertError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot insert at index {} into container of length {}", self.index, self.len)
+    }
+}
+
+impl<T: fmt::Debug> Error for InsertError<T> {}
+
+/// A value could not be stored because the cell was already initialized.
+///
+/// The value is handed back, so it is not lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyInitialized<T> {
+    /// The value that was to be stored.
+    pub value: T,
+}
+
+impl<T> AlreadyInitialized<T> {
+    /// Returns the value that was to be stored.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Display for AlreadyInitialized<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("cell is already initialized")
+    }
+}
+
+impl<T: fmt::Debug> Error for AlreadyInitialized<T> {}
+
+/// An error that occurred while encoding or decoding the [wire format].
+///
+/// [wire format]: crate::wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    /// The output buffer is too small for the encoded data.
+    BufferTooSmall,
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// The input contains a value that is out of range for its type, or a presence flag for a
+    /// slot that does not exist.
+    InvalidValue,
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            WireError::BufferTooSmall => "output buffer too small",
+            WireError::UnexpectedEnd => "unexpected end of input",
+            WireError::InvalidValue => "invalid value in input",
+        })
+    }
+}
+
+impl Error for WireError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poison::PoisonDetected;
+    use std::boxed::Box;
+    use std::string::ToString;
+
+    fn fails() -> Result<(), Box<dyn Error>> {
+        Err(PoisonDetected)?
+    }
+
+    #[test]
+    fn display() {
+        let err = IndexOutOfBounds { index: 4, len: 3 };
+        assert_eq!(err.to_string(), "index 4 out of bounds for length 3");
+        let err = InsertError { index: 4, len: 3, value: 'x' };
+        assert_eq!(err.to_string(), "cannot insert at index 4 into container of length 3");
+        assert_eq!(WireError::UnexpectedEnd.to_string(), "unexpected end of input");
+        let err = AlreadyInitialized { value: 1u8 };
+        assert_eq!(err.to_string(), "cell is already initialized");
+        assert_eq!(err.into_value(), 1);
+        assert_eq!(fails().unwrap_err().to_string(), "option holds the poison pattern");
+    }
+}
@@ -0,0 +1,303 @@
+//! An untagged option paired with its presence flag.
+
+use core::fmt;
+use core::option;
+
+use UntaggedOption;
+
+/// An [`UntaggedOption`] together with a flag recording whether it holds a value.
+///
+/// This is the safe counterpart of the flag-assisted methods on `UntaggedOption`: since the flag
+/// always matches the contents, all operations are safe and the value is dropped along with the
+/// `Flagged`. The flag can be moved out of the option (and packed with other flags, as
+/// [`OptionGroup`] does) when the space matters; `Flagged` is for when it doesn't.
+///
+/// With the `nightly` feature, `Flagged` implements [`Try`], so `?` can be used in functions
+/// returning it. Applying `?` to an empty `Flagged` or `None` returns an empty `Flagged` from the
+/// function:
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::Flagged;
+/// # #[cfg(not(feature = "nightly"))]
+/// # fn main() {}
+/// # #[cfg(feature = "nightly")]
+/// # fn main() {
+/// fn decode(bytes: &[u8]) -> Flagged<u16> {
+///     let lo = *bytes.first()?;
+///     let hi = *bytes.get(1)?;
+///     Flagged::some(u16::from(hi) << 8 | u16::from(lo))
+/// }
+///
+/// assert_eq!(decode(&[0x34, 0x12]).into_option(), Some(0x1234));
+/// assert_eq!(decode(&[0x34]).into_option(), None);
+/// # }
+/// ```
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+/// [`OptionGroup`]: crate::OptionGroup
+/// [`Try`]: core::ops::Try
+pub struct Flagged<T> {
+    is_some: bool,
+    slot: UntaggedOption<T>,
+}
+
+impl<T> Flagged<T> {
+    /// Creates an empty `Flagged`.
+    pub const fn none() -> Self {
+        Flagged {
+            is_some: false,
+            slot: UntaggedOption::none(),
+        }
+    }
+
+    /// Creates a `Flagged` holding `value`.
+    pub const fn some(value: T) -> Self {
+        Flagged {
+            is_some: true,
+            slot: UntaggedOption::some(value),
+        }
+    }
+
+    /// Returns whether a value is stored.
+    pub fn is_some(&self) -> bool {
+        self.is_some
+    }
+
+    /// Returns whether no value is stored.
+    pub fn is_none(&self) -> bool {
+        !self.is_some
+    }
+
+    /// Returns a reference to the stored value, if any.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_some {
+            unsafe { Some(self.slot.as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the stored value, if any.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.is_some {
+            unsafe { Some(self.slot.as_mut()) }
+        } else {
+            None
+        }
+    }
+
+    /// Takes the stored value out, leaving `self` empty.
+    pub fn take(&mut self) -> Option<T> {
+        if self.is_some {
+            self.is_some = false;
+            unsafe { Some(self.slot.take()) }
+        } else {
+            None
+        }
+    }
+
+    /// Stores `value`, returning the previously stored value, if any.
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        let old = self.take();
+        self.slot = UntaggedOption::some(value);
+        self.is_some = true;
+        old
+    }
+
+    /// Returns a reference to the stored value, initializing it with `f` first if `self` is
+    /// empty.
+    ///
+    /// If `f` returns an error, `self` stays empty and the error is returned, so the
+    /// initialization can be retried later.
+    pub fn get_or_try_init<E, F>(&mut self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        unsafe { self.slot.get_or_try_init(&mut self.is_some, f) }
+    }
+
+    /// Returns an iterator over the stored value, yielding it once if present.
+    pub fn iter(&self) -> option::IntoIter<&T> {
+        self.get().into_iter()
+    }
+
+    /// Returns an iterator over a mutable reference to the stored value, if present.
+    pub fn iter_mut(&mut self) -> option::IntoIter<&mut T> {
+        self.get_mut().into_iter()
+    }
+
+    /// Converts `self` into an `Option`.
+    pub fn into_option(mut self) -> Option<T> {
+        self.take()
+    }
+}
+
+impl<T> Default for Flagged<T> {
+    fn default() -> Self {
+        Flagged::none()
+    }
+}
+
+impl<T> Drop for Flagged<T> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+impl<T> IntoIterator for Flagged<T> {
+    type Item = T;
+    type IntoIter = option::IntoIter<T>;
+
+    fn into_iter(self) -> option::IntoIter<T> {
+        self.into_option().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Flagged<T> {
+    type Item = &'a T;
+    type IntoIter = option::IntoIter<&'a T>;
+
+    fn into_iter(self) -> option::IntoIter<&'a T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Flagged<T> {
+    type Item = &'a mut T;
+    type IntoIter = option::IntoIter<&'a mut T>;
+
+    fn into_iter(self) -> option::IntoIter<&'a mut T> {
+        self.iter_mut()
+    }
+}
+
+impl<T> From<Option<T>> for Flagged<T> {
+    fn from(opt: Option<T>) -> Self {
+        match opt {
+            Some(value) => Flagged::some(value),
+            None => Flagged::none(),
+        }
+    }
+}
+
+impl<T> From<Flagged<T>> for Option<T> {
+    fn from(flagged: Flagged<T>) -> Self {
+        flagged.into_option()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Flagged<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("Some").field(value).finish(),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+/// The `Try` implementation, which is unstable.
+#[cfg(feature = "nightly")]
+mod try_impl {
+    use core::convert::Infallible;
+    use core::ops::{ControlFlow, FromResidual, Residual, Try};
+
+    use super::Flagged;
+
+    impl<T> Try for Flagged<T> {
+        type Output = T;
+        type Residual = Flagged<Infallible>;
+
+        fn from_output(output: T) -> Self {
+            Flagged::some(output)
+        }
+
+        fn branch(self) -> ControlFlow<Flagged<Infallible>, T> {
+            match self.into_option() {
+                Some(value) => ControlFlow::Continue(value),
+                None => ControlFlow::Break(Flagged::none()),
+            }
+        }
+    }
+
+    impl<T> FromResidual<Flagged<Infallible>> for Flagged<T> {
+        fn from_residual(_: Flagged<Infallible>) -> Self {
+            Flagged::none()
+        }
+    }
+
+    impl<T> Residual<T> for Flagged<Infallible> {
+        type TryType = Flagged<T>;
+    }
+
+    impl<T> FromResidual<Option<Infallible>> for Flagged<T> {
+        fn from_residual(_: Option<Infallible>) -> Self {
+            Flagged::none()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use std::vec::Vec;
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl<'a> Drop for DropCounter<'a> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    fn add(a: Flagged<u8>, b: Option<u8>) -> Flagged<u8> {
+        let sum = a? + b?;
+        Flagged::some(sum)
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn question_mark() {
+        assert_eq!(add(Flagged::some(1), Some(2)).into_option(), Some(3));
+        assert!(add(Flagged::none(), Some(2)).is_none());
+        assert!(add(Flagged::some(1), None).is_none());
+    }
+
+    #[test]
+    fn iterate() {
+        let mut flagged = Flagged::some(1);
+        for value in &mut flagged {
+            *value += 1;
+        }
+        assert_eq!(flagged.iter().count(), 1);
+
+        let values: Vec<_> = Flagged::none()
+            .into_iter()
+            .chain(flagged)
+            .chain(Flagged::some(3))
+            .collect();
+        assert_eq!(values, [2, 3]);
+        assert_eq!(Flagged::<u8>::none().iter().next(), None);
+    }
+
+    #[test]
+    fn access_and_drop() {
+        let drops = Cell::new(0);
+        {
+            let mut flagged = Flagged::none();
+            assert!(flagged.replace(DropCounter(&drops)).is_none());
+            drop(flagged.replace(DropCounter(&drops)));
+            assert_eq!(drops.get(), 1);
+            assert!(flagged.get().is_some());
+        }
+        assert_eq!(drops.get(), 2);
+
+        let mut flagged = Flagged::none();
+        assert_eq!(flagged.get_or_try_init(|| Err(())), Err(()));
+        assert_eq!(flagged.get_or_try_init(|| Ok::<_, ()>(5)), Ok(&5));
+        *flagged.get_mut().unwrap() += 1;
+        assert_eq!(Option::from(flagged), Some(6));
+    }
+}
@@ -0,0 +1,339 @@
+//! An option that is initialized once and is immutable afterwards.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use error::AlreadyInitialized;
+use sync;
+use UntaggedOption;
+
+/// No value has been stored.
+const EMPTY: u8 = 0;
+/// A value is being written by `try_init`.
+const INITIALIZING: u8 = 1;
+/// The value is initialized and will not be modified again.
+const FROZEN: u8 = 2;
+
+/// An option that can be initialized once and is frozen afterwards.
+///
+/// After a value has been stored with [`init`] or [`try_init`], a `FrozenOption` only hands out
+/// shared references to it. There is no way to modify or remove the value through a shared
+/// reference, so references obtained from a `FrozenOption` in a `static` can be freely shared
+/// between tasks, threads and interrupt handlers. [`untagged_static!`] declares such statics
+/// together with accessor functions.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::FrozenOption;
+/// # fn main() {
+/// static SERIAL: FrozenOption<[u8; 4]> = FrozenOption::new();
+///
+/// assert_eq!(SERIAL.get(), None);
+/// let serial: &'static [u8; 4] = SERIAL.init(*b"A113");
+/// assert_eq!(SERIAL.get(), Some(serial));
+/// assert!(SERIAL.try_init(*b"B000").is_err());
+/// # }
+/// ```
+///
+/// [`init`]: #method.init
+/// [`untagged_static!`]: crate::untagged_static
+/// [`try_init`]: #method.try_init
+pub struct FrozenOption<T> {
+    state: AtomicU8,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for FrozenOption<T> {}
+
+impl<T> FrozenOption<T> {
+    /// Creates an empty `FrozenOption`.
+    pub const fn new() -> Self {
+        FrozenOption {
+            state: AtomicU8::new(EMPTY),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Stores `value` and freezes `self`, returning a reference to the stored value.
+    ///
+    /// If a value has already been stored (or is being stored concurrently), `value` is returned
+    /// in an [`AlreadyInitialized`] error.
+    ///
+    /// [`AlreadyInitialized`]: crate::AlreadyInitialized
+    pub fn try_init(&self, value: T) -> Result<&T, AlreadyInitialized<T>> {
+        let claimed = sync::compare_exchange_u8(
+            &self.state,
+            EMPTY,
+            INITIALIZING,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        );
+        if claimed.is_err() {
+            return Err(AlreadyInitialized { value });
+        }
+
+        unsafe {
+            // Safe: winning the exchange above grants exclusive access to the slot.
+            *self.slot.get() = UntaggedOption::some(value);
+            self.state.store(FROZEN, Ordering::Release);
+            Ok((*self.slot.get()).as_ref())
+        }
+    }
+
+    /// Stores `value` and freezes `self`, returning a reference to the stored value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a value has already been stored (or is being stored concurrently).
+    pub fn init(&self, value: T) -> &T {
+        match self.try_init(value) {
+            Ok(value) => value,
+            Err(_) => panic!("`FrozenOption` initialized twice"),
+        }
+    }
+
+    /// Returns whether a value has been stored and frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.state.load(Ordering::Acquire) == FROZEN
+    }
+
+    /// Returns a reference to the frozen value, or `None` if no value has been stored yet.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_frozen() {
+            unsafe { Some(self.get_unchecked()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the frozen value, or stores and freezes the value returned by `f` if there is none.
+    ///
+    /// If `f` fails, `self` is left empty and its error is returned, so the initialization can be
+    /// retried later. If another value is stored while `f` runs, that value is kept, the value
+    /// returned by `f` is dropped, and this waits until the other store has finished.
+    pub fn get_or_try_init<E, F>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        match self.try_init(f()?) {
+            Ok(value) => Ok(value),
+            Err(_) => loop {
+                if let Some(value) = self.get() {
+                    return Ok(value);
+                }
+                hint::spin_loop();
+            },
+        }
+    }
+
+    /// Returns a reference to the frozen value without checking whether there is one.
+    ///
+    /// # Safety
+    ///
+    /// A value must have been stored, and that store must be visible to the calling thread (for
+    /// example because `get` or `is_frozen` observed it before).
+    pub unsafe fn get_unchecked(&self) -> &T {
+        (*self.slot.get()).as_ref()
+    }
+
+    /// Consumes `self` and returns the stored value, if any.
+    pub fn into_inner(self) -> Option<T> {
+        let mut this = ManuallyDrop::new(self);
+        if *this.state.get_mut() == FROZEN {
+            unsafe { Some(this.slot.get_mut().take()) }
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for FrozenOption<T> {
+    fn default() -> Self {
+        FrozenOption::new()
+    }
+}
+
+impl<T> Drop for FrozenOption<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == FROZEN {
+            unsafe { self.slot.get_mut().take(); }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FrozenOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("FrozenOption").field(&self.get()).finish()
+    }
+}
+
+/// Declares `static` items that are initialized once at runtime and then shared.
+///
+/// `untagged_static!(static NAME: Type;)` declares a type `NAME` with the functions
+/// `NAME::init(Type) -> &'static Type`, `NAME::try_init`, `NAME::get() -> &'static Type` and
+/// `NAME::try_get() -> Option<&'static Type>`. They store the value in a hidden
+/// [`FrozenOption`], so no `unsafe` is needed, a second initialization is detected at runtime, and
+/// `Type` must be `Send` and `Sync`. `init` and `get` panic when the value has already been, or
+/// has not yet been, initialized.
+///
+/// Any number of items can be declared in one invocation, and each can have attributes and a
+/// visibility, which applies to the generated functions.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate untagged_option;
+///
+/// struct Uart {
+///     baud: u32,
+/// }
+///
+/// untagged_static! {
+///     static UART: Uart;
+///     pub static BOARD_ID: [u8; 4];
+/// }
+///
+/// fn main() {
+///     assert!(UART::try_get().is_none());
+///     UART::init(Uart { baud: 115_200 });
+///     assert_eq!(UART::get().baud, 115_200);
+///     assert!(UART::try_init(Uart { baud: 9600 }).is_err());
+///
+///     BOARD_ID::init(*b"RB01");
+///     assert_eq!(BOARD_ID::try_get(), Some(b"RB01"));
+/// }
+/// ```
+///
+/// [`FrozenOption`]: crate::FrozenOption
+#[macro_export]
+macro_rules! untagged_static {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty;)*) => {$(
+        $(#[$attr])*
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        $vis enum $name {}
+
+        impl $name {
+            fn slot() -> &'static $crate::FrozenOption<$ty> {
+                static SLOT: $crate::FrozenOption<$ty> = $crate::FrozenOption::new();
+                &SLOT
+            }
+
+            /// Initializes the value, panicking if it was initialized before.
+            #[allow(dead_code)]
+            $vis fn init(value: $ty) -> &'static $ty {
+                match Self::slot().try_init(value) {
+                    Ok(value) => value,
+                    Err(_) => panic!(concat!("`", stringify!($name), "` initialized twice")),
+                }
+            }
+
+            /// Initializes the value, or returns `value` in an error if it was initialized before.
+            #[allow(dead_code)]
+            $vis fn try_init(value: $ty) -> Result<&'static $ty, $crate::AlreadyInitialized<$ty>> {
+                Self::slot().try_init(value)
+            }
+
+            /// Returns the value, panicking if it has not been initialized.
+            #[allow(dead_code)]
+            $vis fn get() -> &'static $ty {
+                match Self::slot().get() {
+                    Some(value) => value,
+                    None => {
+                        panic!(concat!("`", stringify!($name), "` used before initialization"))
+                    }
+                }
+            }
+
+            /// Returns the value, or `None` if it has not been initialized.
+            #[allow(dead_code)]
+            $vis fn try_get() -> Option<&'static $ty> {
+                Self::slot().get()
+            }
+        }
+    )*};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+    use std::rc::Rc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn init_once() {
+        let opt = FrozenOption::new();
+        assert!(!opt.is_frozen());
+        assert_eq!(opt.try_init(1), Ok(&1));
+        assert_eq!(opt.try_init(2), Err(AlreadyInitialized { value: 2 }));
+        assert_eq!(opt.get(), Some(&1));
+        assert_eq!(unsafe { *opt.get_unchecked() }, 1);
+        assert_eq!(opt.into_inner(), Some(1));
+        assert_eq!(FrozenOption::<u8>::new().into_inner(), None);
+    }
+
+    #[test]
+    fn get_or_try_init() {
+        let opt = FrozenOption::new();
+        assert_eq!(opt.get_or_try_init(|| Err("offline")), Err("offline"));
+        assert!(!opt.is_frozen());
+        assert_eq!(opt.get_or_try_init(|| Ok::<_, ()>(3)), Ok(&3));
+        assert_eq!(opt.get_or_try_init(|| Err(())), Ok(&3));
+        // A value stored from within `f` takes precedence.
+        let other = FrozenOption::new();
+        assert_eq!(other.get_or_try_init(|| Ok::<_, ()>(*other.init(5) + 1)), Ok(&5));
+    }
+
+    #[test]
+    fn drops_value() {
+        let value = Rc::new(());
+        let opt = FrozenOption::new();
+        opt.init(value.clone());
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(opt);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    untagged_static! {
+        static FIRST: u32;
+        #[doc(hidden)]
+        pub(crate) static SECOND: &'static str;
+    }
+
+    #[test]
+    fn statics() {
+        assert_eq!(FIRST::try_get(), None);
+        assert_eq!(*FIRST::init(7), 7);
+        assert_eq!(FIRST::try_init(8).map_err(AlreadyInitialized::into_value), Err(8));
+        assert_eq!(*FIRST::get(), 7);
+
+        let result = panic::catch_unwind(SECOND::get);
+        assert!(result.is_err());
+        SECOND::init("second");
+        assert_eq!(SECOND::try_get(), Some(&"second"));
+    }
+
+    #[test]
+    fn concurrent_init() {
+        let opt = FrozenOption::new();
+        let opt = &opt;
+        let winners = thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| s.spawn(move || opt.try_init(i).is_ok()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).filter(|&won| won).count()
+        });
+        assert_eq!(winners, 1);
+        assert!(opt.get().is_some());
+    }
+}
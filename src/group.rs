@@ -0,0 +1,410 @@
+//! Struct-of-arrays storage for a fixed number of optional values.
+
+use core::fmt;
+use core::iter::Enumerate;
+use core::mem::replace;
+use core::slice;
+
+use bitmap::{self, OptionBitmap, SetBits};
+use error::InsertError;
+use UntaggedOption;
+
+/// A fixed-size group of `N` optional values of type `T`.
+///
+/// The values are stored untagged in one contiguous array, and their presence flags are packed
+/// into a separate [`OptionBitmap`] of `W` words. This costs one bit per slot instead of the
+/// discriminant (and padding) of every `Option<T>` in an `[Option<T>; N]`, and lets presence be
+/// scanned without touching the payloads.
+///
+/// `W` must be large enough to hold `N` flags (see [`bitmap_words`]). The default of one word
+/// covers groups of up to 32 values.
+///
+/// Unlike a bare `UntaggedOption`, the group knows which slots are occupied, so its API is safe
+/// and occupied slots are dropped along with the group.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::OptionGroup;
+/// # fn main() {
+/// let mut group = OptionGroup::<&str, 4>::new();
+/// group.insert(1, "one");
+/// group.insert(3, "three");
+///
+/// assert_eq!(group.get(1), Some(&"one"));
+/// assert_eq!(group.get(2), None);
+/// assert_eq!(group.remove(3), Some("three"));
+///
+/// let slots: Vec<_> = group.iter().collect();
+/// assert_eq!(slots, [None, Some(&"one"), None, None]);
+/// # }
+/// ```
+///
+/// [`OptionBitmap`]: crate::OptionBitmap
+/// [`bitmap_words`]: crate::bitmap_words
+pub struct OptionGroup<T, const N: usize, const W: usize = 1> {
+    flags: OptionBitmap<W>,
+    values: [UntaggedOption<T>; N],
+}
+
+impl<T, const N: usize, const W: usize> OptionGroup<T, N, W> {
+    /// Creates a group with all slots empty.
+    pub const fn new() -> Self {
+        const { assert!(N <= W * ::bitmap::WORD_BITS, "bitmap too small for group") };
+
+        OptionGroup {
+            flags: OptionBitmap::new(),
+            values: [const { UntaggedOption::none() }; N],
+        }
+    }
+
+    /// Returns the number of slots in the group.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns whether slot `index` holds a value.
+    ///
+    /// Returns `false` if `index` is out of bounds.
+    pub fn is_present(&self, index: usize) -> bool {
+        index < N && self.flags.get(index)
+    }
+
+    /// Returns a reference to the value in slot `index`, if there is one.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if self.is_present(index) {
+            unsafe { Some(self.values[index].as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value in slot `index`, if there is one.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if self.is_present(index) {
+            unsafe { Some(self.values[index].as_mut()) }
+        } else {
+            None
+        }
+    }
+
+    /// Stores `value` in slot `index`, returning the value previously stored there.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn insert(&mut self, index: usize, value: T) -> Option<T> {
+        assert!(index < N, "slot index out of bounds");
+
+        if self.flags.get(index) {
+            unsafe { Some(replace(self.values[index].as_mut(), value)) }
+        } else {
+            self.values[index] = UntaggedOption::some(value);
+            self.flags.set(index);
+            None
+        }
+    }
+
+    /// Stores `value` in slot `index`, returning the value previously stored there.
+    ///
+    /// This is the non-panicking version of [`insert`]: if `index` is out of bounds, `value` is
+    /// handed back in the error.
+    ///
+    /// [`insert`]: #method.insert
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<Option<T>, InsertError<T>> {
+        if index < N {
+            Ok(self.insert(index, value))
+        } else {
+            Err(InsertError {
+                index,
+                len: N,
+                value,
+            })
+        }
+    }
+
+    /// Removes and returns the value in slot `index`, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if self.is_present(index) {
+            self.flags.clear(index);
+            unsafe { Some(self.values[index].take()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of occupied slots.
+    pub fn count(&self) -> usize {
+        self.flags.count()
+    }
+
+    /// Returns whether no slot is occupied.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// Returns the index of the first empty slot, if any.
+    ///
+    /// The flags are scanned a word at a time, so this takes `O(W)` rather than `O(N)` time.
+    pub fn first_vacant(&self) -> Option<usize> {
+        self.flags.first_clear().filter(|&index| index < N)
+    }
+
+    /// Returns the index of the first occupied slot, if any.
+    pub fn first_present(&self) -> Option<usize> {
+        self.flags.first_set()
+    }
+
+    /// Returns the index of the last occupied slot, if any.
+    pub fn last_present(&self) -> Option<usize> {
+        self.flags.last_set()
+    }
+
+    /// Returns an iterator over the indices of all occupied slots, in ascending order.
+    pub fn iter_present_indices(&self) -> SetBits<'_> {
+        self.flags.iter_set()
+    }
+
+    /// Removes all values from the group, passing each one to `f` along with its index.
+    ///
+    /// Values are visited in ascending index order, in a single pass over the presence flags.
+    pub fn take_all<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, T),
+    {
+        unsafe { bitmap::take_all(&mut self.values, &mut self.flags, f) }
+    }
+
+    /// Returns the presence flags of the group.
+    pub fn flags(&self) -> &OptionBitmap<W> {
+        &self.flags
+    }
+
+    /// Returns the untagged value array of the group.
+    ///
+    /// Slot `i` holds a value exactly when bit `i` of [`flags`] is set.
+    ///
+    /// [`flags`]: #method.flags
+    pub fn values(&self) -> &[UntaggedOption<T>; N] {
+        &self.values
+    }
+
+    /// Splits the group into mutable references to its flags and its untagged values.
+    ///
+    /// # Safety
+    ///
+    /// When the returned references are dropped, bit `i` of the flags must be set exactly when
+    /// value `i` holds a valid `T`.
+    pub unsafe fn split_mut(&mut self) -> (&mut OptionBitmap<W>, &mut [UntaggedOption<T>; N]) {
+        (&mut self.flags, &mut self.values)
+    }
+
+    /// Writes the occupancy of the group to `out`, for diagnostics.
+    ///
+    /// This prints the number of occupied slots, the capacity and one character per slot: `x` for
+    /// an occupied slot and `.` for an empty one. The values themselves are never printed.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "OptionGroup len={} capacity={} slots=", self.count(), N)?;
+        bitmap::write_flags(out, (0..N).map(|i| self.flags.get(i)))
+    }
+
+    /// Returns an iterator over all slots, yielding `Some` for occupied and `None` for empty ones.
+    pub fn iter(&self) -> Iter<'_, T, W> {
+        Iter {
+            flags: &self.flags,
+            values: self.values.iter().enumerate(),
+        }
+    }
+
+    /// Returns an iterator over all slots that allows modifying the contained values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, W> {
+        IterMut {
+            flags: &self.flags,
+            values: self.values.iter_mut().enumerate(),
+        }
+    }
+}
+
+impl<T, const N: usize, const W: usize> Default for OptionGroup<T, N, W> {
+    fn default() -> Self {
+        OptionGroup::new()
+    }
+}
+
+impl<T, const N: usize, const W: usize> Drop for OptionGroup<T, N, W> {
+    fn drop(&mut self) {
+        self.take_all(|_, value| drop(value));
+    }
+}
+
+/// Iterator over the slots of an [`OptionGroup`].
+///
+/// [`OptionGroup`]: crate::OptionGroup
+pub struct Iter<'a, T: 'a, const W: usize> {
+    flags: &'a OptionBitmap<W>,
+    values: Enumerate<slice::Iter<'a, UntaggedOption<T>>>,
+}
+
+impl<'a, T, const W: usize> Iterator for Iter<'a, T, W> {
+    type Item = Option<&'a T>;
+
+    fn next(&mut self) -> Option<Option<&'a T>> {
+        let flags = self.flags;
+        self.values.next().map(|(i, value)| {
+            if flags.get(i) {
+                unsafe { Some(value.as_ref()) }
+            } else {
+                None
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a, T, const W: usize> ExactSizeIterator for Iter<'a, T, W> {}
+
+/// Mutable iterator over the slots of an [`OptionGroup`].
+///
+/// [`OptionGroup`]: crate::OptionGroup
+pub struct IterMut<'a, T: 'a, const W: usize> {
+    flags: &'a OptionBitmap<W>,
+    values: Enumerate<slice::IterMut<'a, UntaggedOption<T>>>,
+}
+
+impl<'a, T, const W: usize> Iterator for IterMut<'a, T, W> {
+    type Item = Option<&'a mut T>;
+
+    fn next(&mut self) -> Option<Option<&'a mut T>> {
+        let flags = self.flags;
+        self.values.next().map(|(i, value)| {
+            if flags.get(i) {
+                unsafe { Some(value.as_mut()) }
+            } else {
+                None
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a, T, const W: usize> ExactSizeIterator for IterMut<'a, T, W> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use std::string::String;
+    use std::vec::Vec;
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl<'a> Drop for DropCounter<'a> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut group = OptionGroup::<u32, 40, 2>::new();
+        assert_eq!(group.insert(35, 1), None);
+        assert_eq!(group.insert(35, 2), Some(1));
+        assert!(group.is_present(35));
+        assert!(group.flags().get(35));
+        *group.get_mut(35).unwrap() += 1;
+        assert_eq!(group.remove(35), Some(3));
+        assert_eq!(group.remove(35), None);
+        assert!(!group.is_present(40));
+        let err = group.try_insert(40, 9).unwrap_err();
+        assert_eq!((err.index, err.len, err.into_value()), (40, 40, 9));
+        assert_eq!(group.try_insert(0, 9), Ok(None));
+        assert_eq!(group.get(1000), None);
+    }
+
+    #[test]
+    fn presence_queries() {
+        let mut group = OptionGroup::<u8, 33, 2>::new();
+        assert!(group.is_empty());
+        assert_eq!(group.first_vacant(), Some(0));
+        assert_eq!(group.first_present(), None);
+
+        for i in 0..33 {
+            group.insert(i, i as u8);
+        }
+        assert_eq!(group.count(), 33);
+        assert_eq!(group.first_vacant(), None);
+
+        group.remove(0);
+        group.remove(20);
+        assert_eq!(group.first_vacant(), Some(0));
+        assert_eq!(group.first_present(), Some(1));
+        assert_eq!(group.last_present(), Some(32));
+        assert_eq!(group.iter_present_indices().filter(|&i| i < 22).count(), 20);
+        assert_eq!(group.iter_present_indices().last(), Some(32));
+    }
+
+    #[test]
+    fn static_group() {
+        static GROUP: OptionGroup<u32, 64, 2> = OptionGroup::new();
+        assert!(GROUP.is_empty());
+        assert_eq!(GROUP.first_vacant(), Some(0));
+    }
+
+    #[test]
+    fn take_all() {
+        let mut group = OptionGroup::<char, 5>::new();
+        group.insert(4, 'b');
+        group.insert(1, 'a');
+        let mut taken = Vec::new();
+        group.take_all(|i, c| taken.push((i, c)));
+        assert_eq!(taken, [(1, 'a'), (4, 'b')]);
+        assert!(group.is_empty());
+    }
+
+    #[test]
+    fn iterate() {
+        let mut group = OptionGroup::<u8, 3>::new();
+        group.insert(0, 10);
+        group.insert(2, 12);
+        for value in group.iter_mut().flatten() {
+            *value += 1;
+        }
+        let mut iter = group.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(Some(&11)));
+        assert_eq!(iter.next(), Some(None));
+        assert_eq!(iter.next(), Some(Some(&13)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn dump() {
+        let mut group = OptionGroup::<u8, 5>::new();
+        group.insert(0, 1);
+        group.insert(3, 2);
+        let mut out = String::new();
+        group.dump(&mut out).unwrap();
+        assert_eq!(out, "OptionGroup len=2 capacity=5 slots=x..x.");
+    }
+
+    #[test]
+    fn drops_occupied_slots() {
+        let drops = Cell::new(0);
+        {
+            let mut group = OptionGroup::<DropCounter, 8>::new();
+            group.insert(1, DropCounter(&drops));
+            group.insert(6, DropCounter(&drops));
+            group.insert(7, DropCounter(&drops));
+            drop(group.remove(7));
+            assert_eq!(drops.get(), 1);
+        }
+        assert_eq!(drops.get(), 3);
+    }
+}
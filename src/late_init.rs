@@ -0,0 +1,133 @@
+//! Late initialization of values that are read everywhere afterwards.
+
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+#[cfg(debug_assertions)]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use UntaggedOption;
+
+/// A value that is initialized once at runtime and dereferenced freely afterwards.
+///
+/// `LateInit` formalizes the common embedded pattern of a `static` that is initialized in `main`
+/// before interrupts are enabled, and then read from everywhere. It derefs to the contained `T`
+/// without any runtime overhead in release builds. With debug assertions enabled, it tracks
+/// initialization and panics on dereferences before [`init`] and on repeated initialization.
+///
+/// Since dereferencing is safe, the responsibility of initializing the value in time lies with
+/// the creator of the `LateInit`, which is why [`new`] is `unsafe`.
+///
+/// Like a bare `UntaggedOption`, a `LateInit` does not know whether it is initialized in release
+/// builds, so the contained value is never dropped.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::LateInit;
+/// # fn main() {
+/// struct Config {
+///     clock_hz: u32,
+/// }
+///
+/// // Safe: `CONFIG` is initialized at the start of `main`, before it is accessed anywhere else.
+/// static CONFIG: LateInit<Config> = unsafe { LateInit::new() };
+///
+/// // Safe: no other accesses happen concurrently, and `CONFIG` was not initialized before.
+/// unsafe { CONFIG.init(Config { clock_hz: 48_000_000 }); }
+///
+/// assert_eq!(CONFIG.clock_hz, 48_000_000);
+/// # }
+/// ```
+///
+/// [`init`]: #method.init
+/// [`new`]: #method.new
+pub struct LateInit<T> {
+    slot: UnsafeCell<UntaggedOption<T>>,
+    #[cfg(debug_assertions)]
+    initialized: AtomicBool,
+}
+
+unsafe impl<T: Send + Sync> Sync for LateInit<T> {}
+
+impl<T> LateInit<T> {
+    /// Creates an uninitialized `LateInit`.
+    ///
+    /// # Safety
+    ///
+    /// The returned `LateInit` must be initialized by calling [`init`] before it is dereferenced
+    /// for the first time.
+    ///
+    /// [`init`]: #method.init
+    pub const unsafe fn new() -> Self {
+        LateInit {
+            slot: UnsafeCell::new(UntaggedOption::none()),
+            #[cfg(debug_assertions)]
+            initialized: AtomicBool::new(false),
+        }
+    }
+
+    /// Initializes the value.
+    ///
+    /// # Safety
+    ///
+    /// This must be called at most once, and must not run concurrently with any other access to
+    /// `self`.
+    ///
+    /// # Panics
+    ///
+    /// With debug assertions enabled, panics if `self` has already been initialized.
+    pub unsafe fn init(&self, value: T) {
+        #[cfg(debug_assertions)]
+        {
+            assert!(!self.initialized.load(Ordering::Relaxed), "`LateInit` initialized twice");
+        }
+
+        *self.slot.get() = UntaggedOption::some(value);
+
+        #[cfg(debug_assertions)]
+        self.initialized.store(true, Ordering::Release);
+    }
+}
+
+impl<T> Deref for LateInit<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        #[cfg(debug_assertions)]
+        {
+            assert!(
+                self.initialized.load(Ordering::Acquire),
+                "`LateInit` dereferenced before initialization"
+            );
+        }
+
+        // Safe: `new` requires initialization before the first dereference.
+        unsafe { (*self.slot.get()).as_ref() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deref_after_init() {
+        static VALUE: LateInit<[u16; 2]> = unsafe { LateInit::new() };
+        unsafe { VALUE.init([1, 2]); }
+        assert_eq!(VALUE[1], 2);
+        assert_eq!(*VALUE, [1, 2]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn checks_in_debug_builds() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let late = unsafe { LateInit::<u8>::new() };
+        assert!(catch_unwind(AssertUnwindSafe(|| *late)).is_err());
+        unsafe { late.init(1); }
+        assert!(catch_unwind(AssertUnwindSafe(|| unsafe { late.init(2) })).is_err());
+        assert_eq!(*late, 1);
+    }
+}
@@ -1,14 +1,175 @@
 //! Provides an unsafe tagless alternative to `Option<T>` that uses less memory.
 //!
-//! Nightly-only. `#![no_std]`.
+//! Works on stable Rust 1.88 or later. `#![no_std]`.
+//!
+//! # Cargo features
+//!
+//! * `alloc`: helpers for payloads on the heap, such as [`PtrCow`].
+//! * `poison`: fills empty options with the [poison pattern].
+//! * `critical-section`: performs compare-and-swap in a critical section, for targets without
+//!   atomic read-modify-write instructions, and adds [`SharedOption`]. Without it, the types that
+//!   rely on compare-and-swap, such as `FrozenOption`, `StaticCell` and the queues, are not
+//!   available on those targets.
+//! * `bytemuck`: implements `Zeroable` and `AnyBitPattern` for options of suitable types.
+//! * `defmt`: implements `defmt::Format` for the formatting adapters in [`debug`].
+//! * `serde`: (de)serialization of options whose presence is tracked elsewhere, see
+//!   [`serde_support`] and [`always_some`].
+//! * `nightly`: implements the unstable `Try` trait for [`Flagged`], so `?` can be used with it.
+//!   Requires a nightly compiler.
+//!
+//! [`PtrCow`]: crate::ptr_cow::PtrCow
+//! [poison pattern]: crate::poison
+//! [`Flagged`]: crate::Flagged
+//! [`serde_support`]: crate::serde_support
+//! [`always_some`]: crate::always_some
+//! [`debug`]: crate::debug
+//! [`SharedOption`]: crate::shared::SharedOption
 
-#![feature(untagged_unions)]
+#![cfg_attr(feature = "nightly", feature(try_trait_v2, try_trait_v2_residual))]
 
 #![no_std]
 
-use core::mem::{replace, ManuallyDrop};
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(test)]
+extern crate std;
+#[cfg(test)]
+#[macro_use]
+extern crate pin_project_lite;
+
+use core::cell::Cell;
+use core::mem::{self, replace, MaybeUninit};
+use core::ptr;
 
-/// A union which either holds a `T` or nothing.
+use policy::{Kind, Policy, Unchecked};
+
+#[cfg(feature = "serde")]
+pub mod always_some;
+pub mod any_slot;
+pub mod array;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub mod atomic;
+pub mod backup;
+pub mod bitmap;
+pub mod branchless;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub mod broadcast;
+pub mod debug;
+pub mod drop_queue;
+pub mod erased;
+pub mod error;
+pub mod flagged;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub mod frozen;
+pub mod group;
+pub mod late_init;
+pub mod mailbox;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub mod mpmc;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub mod mpsc;
+pub mod no_padding;
+pub mod packed;
+pub mod partial;
+mod pin;
+pub mod poison;
+pub mod policy;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "illumos",
+    target_os = "fuchsia",
+    target_os = "none",
+))]
+#[macro_use]
+pub mod registry;
+pub mod result;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+#[cfg(feature = "critical-section")]
+pub mod shared;
+pub mod slab;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub mod static_cell;
+pub mod string;
+#[cfg(any(target_has_atomic = "8", target_has_atomic = "ptr", feature = "critical-section"))]
+mod sync;
+pub mod transaction;
+pub mod type_map;
+pub mod typestate;
+pub mod unaligned;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub mod versioned;
+pub mod watch;
+pub mod wire;
+pub mod write_once;
+#[cfg(feature = "bytemuck")]
+mod zeroable;
+
+#[cfg(feature = "alloc")]
+mod boxed;
+#[cfg(feature = "alloc")]
+pub mod ptr_cow;
+#[cfg(feature = "alloc")]
+pub mod rc;
+#[cfg(feature = "alloc")]
+pub mod vec;
+
+pub use any_slot::AnySlot;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub use atomic::AtomicUntaggedOption;
+pub use backup::BackupSlot;
+pub use bitmap::{bitmap_words, take_all, OptionBitmap};
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub use broadcast::Broadcast;
+pub use drop_queue::DropQueue;
+pub use erased::ErasedSlot;
+pub use error::{AlreadyInitialized, CapacityExceeded, IndexOutOfBounds, InsertError, WireError};
+pub use flagged::Flagged;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub use frozen::FrozenOption;
+pub use group::OptionGroup;
+pub use late_init::LateInit;
+pub use mailbox::PriorityMailbox;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub use mpmc::MpmcQueue;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub use mpsc::MpscQueue;
+pub use no_padding::NoPadding;
+pub use packed::{PackedOption, Reserved};
+pub use partial::PartialArray;
+pub use poison::PoisonDetected;
+pub use result::UntaggedResult;
+#[cfg(feature = "critical-section")]
+pub use shared::SharedOption;
+pub use slab::UntaggedSlab;
+#[cfg(any(target_has_atomic = "8", feature = "critical-section"))]
+pub use static_cell::StaticCell;
+pub use string::FixedString;
+pub use transaction::TransactionSlot;
+pub use type_map::TypeMap;
+pub use unaligned::UnalignedOption;
+#[cfg(any(target_has_atomic = "ptr", feature = "critical-section"))]
+pub use versioned::VersionedSlot;
+pub use watch::WatchSlot;
+pub use write_once::WriteOnce;
+#[cfg(feature = "alloc")]
+pub use ptr_cow::PtrCow;
+#[cfg(feature = "alloc")]
+pub use vec::VecExt;
+
+/// An option which either holds a `T` or nothing, without a discriminant.
 ///
 /// This can be seen as a `T` that may not be properly initialized.
 ///
@@ -48,20 +209,40 @@ use core::mem::{replace, ManuallyDrop};
 ///
 /// This also applies to assignments: An assignment like `opt = UntaggedOption::none()` will leak
 /// the previously contained value (if any).
-#[allow(unions_with_drop_fields)]
-pub union UntaggedOption<T> {
-    some: ManuallyDrop<T>,
-    none: (),
+///
+/// # Layout
+///
+/// `UntaggedOption<T>` is `#[repr(C)]` and has the same size and alignment as `T`. Arrays of
+/// `UntaggedOption<T>` therefore have the same layout as arrays of `T`.
+///
+/// # Policies
+///
+/// The second type parameter selects a [`Policy`] that can add checks or instrumentation to
+/// individual options. The default, [`Unchecked`], adds nothing and keeps the layout described
+/// above. See the [`policy`] module for the others.
+///
+/// To catch misuse in debug builds only, use [`DebugChecked`], which is [`Checked`] with debug
+/// assertions enabled and `Unchecked` otherwise.
+///
+/// [`Policy`]: crate::policy::Policy
+/// [`Unchecked`]: crate::policy::Unchecked
+/// [`Checked`]: crate::policy::Checked
+/// [`DebugChecked`]: crate::policy::DebugChecked
+/// [`policy`]: crate::policy
+#[repr(C)]
+pub struct UntaggedOption<T, P: Policy = Unchecked> {
+    raw: MaybeUninit<T>,
+    state: P::State,
 }
 
 impl<T> UntaggedOption<T> {
     /// Creates a new `UntaggedOption` holding no value.
     ///
     /// It is not safe to call any method on the resulting `UntaggedOption`.
+    ///
+    /// With the `poison` feature, the storage is filled with the poison pattern.
     pub const fn none() -> Self {
-        UntaggedOption {
-            none: (),
-        }
+        UntaggedOption::none_with_policy()
     }
 
     /// Creates an `UntaggedOption` containing `t`.
@@ -71,8 +252,34 @@ impl<T> UntaggedOption<T> {
     /// When the `UntaggedOption` is dropped, `t` will *not* be dropped automatically. You must call
     /// `take` if you need `t` to be dropped properly.
     pub const fn some(t: T) -> Self {
+        UntaggedOption::some_with_policy(t)
+    }
+}
+
+impl<T, P: Policy> UntaggedOption<T, P> {
+    /// Creates a new `UntaggedOption` with policy `P` holding no value.
+    ///
+    /// This is [`none`] for options with a non-default policy.
+    ///
+    /// [`none`]: #method.none
+    pub const fn none_with_policy() -> Self {
+        let mut opt = UntaggedOption {
+            raw: MaybeUninit::uninit(),
+            state: P::EMPTY,
+        };
+        opt.mark_empty();
+        opt
+    }
+
+    /// Creates an `UntaggedOption` with policy `P` containing `t`.
+    ///
+    /// This is [`some`] for options with a non-default policy.
+    ///
+    /// [`some`]: #method.some
+    pub const fn some_with_policy(t: T) -> Self {
         UntaggedOption {
-            some: ManuallyDrop::new(t),
+            raw: MaybeUninit::new(t),
+            state: P::FULL,
         }
     }
 
@@ -80,38 +287,619 @@ impl<T> UntaggedOption<T> {
     ///
     /// This can be called to drop the contained `T`.
     ///
+    /// With the `poison` feature, the storage is left filled with the poison pattern.
+    ///
+    /// This is a `const fn`, so values can be moved between options during `const` evaluation.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub const unsafe fn take(&mut self) -> T {
+        self.assert_full();
+        self.count_access();
+        let value = ptr::read(self.as_ptr());
+        self.mark_empty();
+        value
+    }
+
+    /// Stores `value` in `self` and returns the value that was stored before.
+    ///
+    /// Like [`take`], this is a `const fn`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`take`]: #method.take
+    /// [`UntaggedOption::some`]: #method.some
+    pub const unsafe fn replace(&mut self, value: T) -> T {
+        let old = self.take();
+        ptr::write(self.as_mut_ptr(), value);
+        self.mark_full();
+        old
+    }
+
+    /// Takes the `T` out of `self` and passes it to `f`, returning the result.
+    ///
+    /// Like [`take`], this leaves `self` empty. Since `f` receives the value directly, the
+    /// compiler can often construct it in `f`'s argument slot instead of copying it through the
+    /// caller's stack, which matters for large payloads.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`take`]: #method.take
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn take_with<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(T) -> R,
+    {
+        f(self.take())
+    }
+
+    /// Replaces the contained `T` with the result of calling `f` on it.
+    ///
+    /// The value is read out and the result written back through raw pointers, without moving
+    /// either through a temporary.
+    ///
+    /// If `f` panics, `self` no longer holds a valid value, and the caller has to treat it as
+    /// empty.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn replace_with<F>(&mut self, f: F)
+    where
+        F: FnOnce(T) -> T,
+    {
+        /// Marks the option as empty if `f` panics.
+        struct Guard<'a, T: 'a, P: Policy + 'a>(&'a mut UntaggedOption<T, P>);
+
+        impl<'a, T, P: Policy> Drop for Guard<'a, T, P> {
+            fn drop(&mut self) {
+                self.0.mark_empty();
+            }
+        }
+
+        self.assert_full();
+        self.count_access();
+        let guard = Guard(self);
+        let value = ptr::read(guard.0.as_ptr());
+        ptr::write(guard.0.as_mut_ptr(), f(value));
+        mem::forget(guard);
+    }
+
+    /// Maps the contained `T` to a `U` by applying `f`, consuming `self`.
+    ///
+    /// The returned option uses the same policy as `self`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn map<U, F>(self, f: F) -> UntaggedOption<U, P>
+    where
+        F: FnOnce(T) -> U,
+    {
+        UntaggedOption::some_with_policy(f(self.into_inner()))
+    }
+
+    /// Calls `f` with the contained `T` and returns the resulting option, consuming `self`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn and_then<U, F>(self, f: F) -> UntaggedOption<U, P>
+    where
+        F: FnOnce(T) -> UntaggedOption<U, P>,
+    {
+        f(self.into_inner())
+    }
+
+    /// Calls `f` with a mutable reference to the contained `T`.
+    ///
+    /// This is a shorthand for `f(self.as_mut())` for chaining transformations that do not change
+    /// the type, see [`map`] for those that do.
+    ///
     /// # Safety
     ///
     /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
     /// such an option.
     ///
+    /// [`map`]: #method.map
     /// [`UntaggedOption::some`]: #method.some
-    pub unsafe fn take(&mut self) -> T {
-        ManuallyDrop::into_inner(replace(self, UntaggedOption::none()).some)
+    pub unsafe fn map_in_place<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        f(self.as_mut())
+    }
+
+    /// Exchanges the contents of `self` and `other`.
+    ///
+    /// The storage is swapped as untyped memory, together with the policy state, so this is safe
+    /// to call in any state.
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(self, other);
+    }
+
+    /// Stores `value` in `self` and returns a mutable reference to it.
+    ///
+    /// Any value held before is overwritten without being dropped. This is safe, since leaking a
+    /// value is, but `self` should usually be empty.
+    pub fn insert(&mut self, value: T) -> &mut T {
+        self.write(value);
+        unsafe { self.as_mut() }
+    }
+
+    /// Moves the contained `T` out of `self`.
+    ///
+    /// This is the counterpart of `Option::unwrap_unchecked`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn into_inner(self) -> T {
+        self.assert_full();
+        self.count_access();
+        self.raw.assume_init()
+    }
+
+    /// Drops the contained `T` in place, leaving `self` empty.
+    ///
+    /// Unlike `drop(opt.take())`, this never moves the value, which matters for large or pinned
+    /// payloads.
+    ///
+    /// With the `poison` feature, the storage is left filled with the poison pattern.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn drop_in_place(&mut self) {
+        self.assert_full();
+        self.count_access();
+        ptr::drop_in_place(self.as_mut_ptr());
+        self.mark_empty();
     }
 
     /// Obtains an immutable reference to the contained `T`.
     ///
+    /// See [`as_ref_validated`] for a variant that checks for the poison pattern.
+    ///
     /// # Safety
     ///
     /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
     /// such an option.
     ///
     /// [`UntaggedOption::some`]: #method.some
+    /// [`as_ref_validated`]: #method.as_ref_validated
     pub unsafe fn as_ref(&self) -> &T {
-        &self.some
+        self.assert_full();
+        self.count_access();
+        self.raw.assume_init_ref()
     }
 
     /// Obtains a mutable reference to the contained `T`.
     ///
+    /// See [`as_mut_validated`] for a variant that checks for the poison pattern.
+    ///
     /// # Safety
     ///
     /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
     /// such an option.
     ///
     /// [`UntaggedOption::some`]: #method.some
+    /// [`as_mut_validated`]: #method.as_mut_validated
     pub unsafe fn as_mut(&mut self) -> &mut T {
-        &mut self.some
+        self.assert_full();
+        self.count_access();
+        self.raw.assume_init_mut()
+    }
+
+    /// Returns a raw pointer to the payload.
+    ///
+    /// The pointer is valid for as long as `self` is, whether or not the payload is initialized.
+    /// Accessing the payload through it never creates a reference to the `T`, which is what
+    /// memory shared with DMA engines or mapped to registers requires. See also [`read`],
+    /// [`write`], [`read_volatile`] and [`write_volatile`].
+    ///
+    /// [`read`]: #method.read
+    /// [`write`]: #method.write
+    /// [`read_volatile`]: #method.read_volatile
+    /// [`write_volatile`]: #method.write_volatile
+    pub const fn as_ptr(&self) -> *const T {
+        self.raw.as_ptr()
+    }
+
+    /// Returns a mutable raw pointer to the payload.
+    ///
+    /// Storing a value through the pointer does not update the state of policies that track
+    /// presence; use [`write`] for that.
+    ///
+    /// [`write`]: #method.write
+    pub const fn as_mut_ptr(&mut self) -> *mut T {
+        self.raw.as_mut_ptr()
+    }
+
+    /// Returns a bitwise copy of the contained `T`, leaving it in place.
+    ///
+    /// Like `ptr::read`, this duplicates the value: unless `T` is `Copy`, only one of the copies
+    /// may be used afterwards.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn read(&self) -> T {
+        self.assert_full();
+        self.count_access();
+        ptr::read(self.as_ptr())
+    }
+
+    /// Stores `value` in `self`, overwriting any value held before without dropping it.
+    pub fn write(&mut self, value: T) {
+        unsafe { ptr::write(self.as_mut_ptr(), value) }
+        self.mark_full();
+    }
+
+    /// Returns a bitwise copy of the contained `T` using a volatile read.
+    ///
+    /// The read is never elided or merged with other accesses by the compiler, so this observes
+    /// values written by hardware behind its back.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn read_volatile(&self) -> T {
+        self.assert_full();
+        self.count_access();
+        ptr::read_volatile(self.as_ptr())
+    }
+
+    /// Stores `value` in `self` using a volatile write, overwriting any value held before without
+    /// dropping it.
+    pub fn write_volatile(&mut self, value: T) {
+        unsafe { ptr::write_volatile(self.as_mut_ptr(), value) }
+        self.mark_full();
+    }
+
+    /// Updates the policy state after the payload was removed or never stored.
+    const fn mark_empty(&mut self) {
+        if Self::has_tag() {
+            unsafe { *(ptr::addr_of_mut!(self.state) as *mut bool) = false }
+        }
+        if Self::is_poisoning() {
+            poison::poison(self);
+        }
+    }
+
+    /// Updates the policy state after a payload was stored.
+    pub(crate) const fn mark_full(&mut self) {
+        if Self::has_tag() {
+            unsafe { *(ptr::addr_of_mut!(self.state) as *mut bool) = true }
+        }
+    }
+
+    /// Returns whether the policy state is a presence tag, which is the case for `Checked`.
+    pub(crate) const fn has_tag() -> bool {
+        matches!(P::KIND, Kind::Checked)
+    }
+
+    /// Returns whether the storage of empty options is filled with the poison pattern.
+    ///
+    /// This is the case for `Poison`, and for the default policy with the `poison` feature.
+    pub(crate) const fn is_poisoning() -> bool {
+        matches!(P::KIND, Kind::Poison)
+            || matches!(P::KIND, Kind::Unchecked) && cfg!(feature = "poison")
+    }
+
+    /// Returns the presence tag, or `None` if the policy does not store one.
+    pub(crate) const fn tag(&self) -> Option<bool> {
+        if Self::has_tag() {
+            Some(unsafe { *(ptr::addr_of!(self.state) as *const bool) })
+        } else {
+            None
+        }
+    }
+
+    /// Panics if the policy knows that `self` is empty.
+    const fn assert_full(&self) {
+        if let Some(false) = self.tag() {
+            panic!("accessed an empty UntaggedOption");
+        }
+    }
+
+    /// Records an access to the payload.
+    const fn count_access(&self) {
+        if let Kind::Counting = P::KIND {
+            unsafe {
+                let count = (*(ptr::addr_of!(self.state) as *const Cell<u32>)).as_ptr();
+                *count = (*count).wrapping_add(1);
+            }
+        }
+    }
+}
+
+impl<T> UntaggedOption<T> {
+    /// Stores `value` in `self` unless it already holds a value, and returns a mutable reference
+    /// to the contained value.
+    ///
+    /// `is_some` tracks whether `self` holds a value and is set to `true` by this method. If `self`
+    /// already held a value, `value` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `*is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn or_insert(&mut self, is_some: &mut bool, value: T) -> &mut T {
+        if !*is_some {
+            *self = UntaggedOption::some(value);
+            *is_some = true;
+        }
+        self.as_mut()
+    }
+
+    /// Returns a mutable reference to the contained value, storing the result of `f` first if
+    /// `self` is empty.
+    ///
+    /// `is_some` tracks whether `self` holds a value and is set to `true` by this method.
+    ///
+    /// # Safety
+    ///
+    /// `*is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn get_or_insert_with<F>(&mut self, is_some: &mut bool, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        if !*is_some {
+            *self = UntaggedOption::some(f());
+            *is_some = true;
+        }
+        self.as_mut()
+    }
+
+    /// Returns a reference to the contained value, initializing it with `f` first if `self` is
+    /// empty.
+    ///
+    /// `is_some` tracks whether `self` holds a value and is set to `true` once initialization
+    /// succeeds. If `f` returns an error, `self` stays empty and the error is returned, so the
+    /// initialization can be retried later.
+    ///
+    /// # Safety
+    ///
+    /// `*is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn get_or_try_init<E, F>(&mut self, is_some: &mut bool, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if !*is_some {
+            *self = UntaggedOption::some(f()?);
+            *is_some = true;
+        }
+        Ok(self.as_ref())
+    }
+
+    /// Takes the value out of `self` or `other` if exactly one of them holds a value.
+    ///
+    /// `is_some` and `other_is_some` track whether `self` and `other` hold a value. The flag of the
+    /// option the value was taken from is cleared. If both or neither option hold a value, both
+    /// are left untouched and `None` is returned.
+    ///
+    /// # Safety
+    ///
+    /// `*is_some` must be `true` if and only if `self` holds a valid `T`, and `*other_is_some`
+    /// must be `true` if and only if `other` holds a valid `T`.
+    pub unsafe fn xor_take(
+        &mut self,
+        is_some: &mut bool,
+        other: &mut Self,
+        other_is_some: &mut bool,
+    ) -> Option<T> {
+        match (*is_some, *other_is_some) {
+            (true, false) => {
+                *is_some = false;
+                Some(self.take())
+            }
+            (false, true) => {
+                *other_is_some = false;
+                Some(other.take())
+            }
+            _ => None,
+        }
+    }
+
+    /// Exchanges the contents of `self` and the regular `Option` `other`.
+    ///
+    /// `is_some` tracks whether `self` holds a value and is updated to reflect the new contents.
+    /// This allows moving values between the untagged representation and `Option`-based APIs
+    /// without going through temporaries.
+    ///
+    /// # Safety
+    ///
+    /// `*is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn swap_with_option(&mut self, is_some: &mut bool, other: &mut Option<T>) {
+        let mine = if *is_some { Some(self.take()) } else { None };
+        match replace(other, mine) {
+            Some(value) => {
+                *self = UntaggedOption::some(value);
+                *is_some = true;
+            }
+            None => *is_some = false,
+        }
+    }
+
+    /// Converts `self` into a regular `Option`, using `is_some` to tell whether it holds a value.
+    ///
+    /// # Safety
+    ///
+    /// `is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn into_option(self, is_some: bool) -> Option<T> {
+        if is_some {
+            Some(self.into_inner())
+        } else {
+            None
+        }
+    }
+
+    /// Converts `Some(value)` into an option holding `value`, without checking for `None`.
+    ///
+    /// Unlike the `From<Option<T>>` impl, this does not branch on the discriminant.
+    ///
+    /// # Safety
+    ///
+    /// `opt` must be `Some`.
+    pub unsafe fn from_option_unchecked(opt: Option<T>) -> Self {
+        UntaggedOption::some(opt.unwrap_unchecked())
+    }
+
+    /// Combines `self` and `other` into a single option holding both values.
+    ///
+    /// This is meant for pairs of options whose presence is governed by the same flag: if both
+    /// hold a value, the result holds the pair; if neither does, the result holds nothing. If only
+    /// one of them holds a value, the result holds no valid value and that value is leaked.
+    ///
+    /// The payloads are moved as untyped memory, so this never reads a `T` or `U` and is safe to
+    /// call in any state.
+    pub fn zip<U>(self, other: UntaggedOption<U>) -> UntaggedOption<(T, U)> {
+        let mut pair = UntaggedOption::none();
+        unsafe {
+            let dest: *mut (T, U) = pair.as_mut_ptr();
+            ptr::copy_nonoverlapping(
+                self.as_ptr() as *const MaybeUninit<T>,
+                ptr::addr_of_mut!((*dest).0) as *mut MaybeUninit<T>,
+                1,
+            );
+            ptr::copy_nonoverlapping(
+                other.as_ptr() as *const MaybeUninit<U>,
+                ptr::addr_of_mut!((*dest).1) as *mut MaybeUninit<U>,
+                1,
+            );
+        }
+        pair
+    }
+}
+
+impl<T: Copy> UntaggedOption<T> {
+    /// Reads the `T` out of the option at `this`, which may be misaligned.
+    ///
+    /// This allows overlaying options onto byte buffers at arbitrary offsets. Since references
+    /// to misaligned values are not allowed, it takes a raw pointer instead of `&self`.
+    ///
+    /// # Safety
+    ///
+    /// `this` must be valid for reads of `size_of::<T>()` bytes, and the option must hold a valid
+    /// `T`. `this` does not need to be aligned.
+    pub unsafe fn read_unaligned(this: *const Self) -> T {
+        ptr::read_unaligned(this as *const T)
+    }
+
+    /// Stores `value` in the option at `this`, which may be misaligned.
+    ///
+    /// Any value held before is overwritten, which is fine since `T` is `Copy`.
+    ///
+    /// # Safety
+    ///
+    /// `this` must be valid for writes of `size_of::<T>()` bytes. It does not need to be aligned.
+    pub unsafe fn write_unaligned(this: *mut Self, value: T) {
+        ptr::write_unaligned(this as *mut T, value)
+    }
+}
+
+impl<T, U> UntaggedOption<(T, U)> {
+    /// Splits an option holding a pair into two options holding one value each.
+    ///
+    /// This is the inverse of [`zip`]: if `self` holds a pair, both results hold a value; if it
+    /// holds nothing, neither result does.
+    ///
+    /// Like [`zip`], this moves the payload as untyped memory and is safe to call in any state.
+    ///
+    /// [`zip`]: #method.zip
+    pub fn unzip(self) -> (UntaggedOption<T>, UntaggedOption<U>) {
+        let mut first = UntaggedOption::none();
+        let mut second = UntaggedOption::none();
+        unsafe {
+            let src: *const (T, U) = self.as_ptr();
+            ptr::copy_nonoverlapping(
+                ptr::addr_of!((*src).0) as *const MaybeUninit<T>,
+                first.as_mut_ptr() as *mut MaybeUninit<T>,
+                1,
+            );
+            ptr::copy_nonoverlapping(
+                ptr::addr_of!((*src).1) as *const MaybeUninit<U>,
+                second.as_mut_ptr() as *mut MaybeUninit<U>,
+                1,
+            );
+        }
+        (first, second)
+    }
+}
+
+impl<T> UntaggedOption<UntaggedOption<T>> {
+    /// Removes one level of nesting.
+    ///
+    /// An `UntaggedOption<UntaggedOption<T>>` has the same layout as an `UntaggedOption<T>`, so
+    /// this is a plain reinterpretation that never reads the payload and is safe to call in any
+    /// state. The result holds a value exactly when both the outer and the inner option did.
+    pub fn flatten(self) -> UntaggedOption<T> {
+        unsafe { self.raw.assume_init() }
+    }
+}
+
+/// Copying an option copies its storage bytewise, whether or not it holds a value.
+impl<T: Copy, P: Policy> Clone for UntaggedOption<T, P>
+where
+    P::State: Copy,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Copy, P: Policy> Copy for UntaggedOption<T, P> where P::State: Copy {}
+
+impl<T, P: Policy> Default for UntaggedOption<T, P> {
+    /// Returns an empty option, like [`none_with_policy`].
+    ///
+    /// [`none_with_policy`]: #method.none_with_policy
+    fn default() -> Self {
+        UntaggedOption::none_with_policy()
+    }
+}
+
+/// Converts `Some(value)` into an option holding `value`, and `None` into an empty option.
+///
+/// The result does not remember which of the two it was, so the caller has to keep track of that
+/// separately. Otherwise, a converted value can not be taken out again and is leaked.
+impl<T, P: Policy> From<Option<T>> for UntaggedOption<T, P> {
+    fn from(opt: Option<T>) -> Self {
+        match opt {
+            Some(value) => UntaggedOption::some_with_policy(value),
+            None => UntaggedOption::none_with_policy(),
+        }
     }
 }
 
@@ -123,11 +911,12 @@ mod tests {
     fn static_context() {
         static mut MY_OPT: UntaggedOption<u8> = UntaggedOption::none();
         unsafe {
-            MY_OPT = UntaggedOption::some(123);
-            assert_eq!(*MY_OPT.as_ref(), 123);
-            *MY_OPT.as_mut() = 42;
-            assert_eq!(*MY_OPT.as_ref(), 42);
-            MY_OPT.take();
+            let opt = &mut *ptr::addr_of_mut!(MY_OPT);
+            *opt = UntaggedOption::some(123);
+            assert_eq!(*opt.as_ref(), 123);
+            *opt.as_mut() = 42;
+            assert_eq!(*opt.as_ref(), 42);
+            opt.take();
         }
     }
 
@@ -145,6 +934,8 @@ mod tests {
         }
 
         let mut opt = UntaggedOption::some(MyDrop);
+        // Dropping the option must not drop the payload, which is what is being tested.
+        #[allow(clippy::drop_non_drop)]
         drop(opt);
         assert_eq!(DROPCOUNT.load(Ordering::SeqCst), 0);
         opt = UntaggedOption::some(MyDrop);
@@ -152,4 +943,236 @@ mod tests {
         unsafe { opt.take(); }
         assert_eq!(DROPCOUNT.load(Ordering::SeqCst), 1);
     }
+
+    #[test]
+    fn map() {
+        let opt = UntaggedOption::some(21u8);
+        unsafe {
+            let mut opt = opt.map(u32::from).map(|x| x * 2);
+            assert_eq!(opt.take(), 42u32);
+        }
+    }
+
+    #[test]
+    fn combinators() {
+        unsafe {
+            let mut opt = UntaggedOption::some(3u8).and_then(|x| UntaggedOption::some([x; 2]));
+            assert_eq!(opt.take(), [3, 3]);
+
+            let mut opt = UntaggedOption::none();
+            let mut is_some = false;
+            assert_eq!(*opt.or_insert(&mut is_some, 1), 1);
+            assert!(is_some);
+            assert_eq!(*opt.or_insert(&mut is_some, 2), 1);
+
+            let mut other = UntaggedOption::none();
+            let mut other_is_some = false;
+            assert_eq!(opt.xor_take(&mut is_some, &mut other, &mut other_is_some), Some(1));
+            assert!(!is_some);
+            assert_eq!(opt.xor_take(&mut is_some, &mut other, &mut other_is_some), None);
+
+            other = UntaggedOption::some(5);
+            other_is_some = true;
+            assert_eq!(opt.xor_take(&mut is_some, &mut other, &mut other_is_some), Some(5));
+            assert!(!other_is_some);
+        }
+    }
+
+    #[test]
+    fn get_or_try_init() {
+        let mut opt = UntaggedOption::none();
+        let mut is_some = false;
+        unsafe {
+            assert_eq!(opt.get_or_try_init(&mut is_some, || Err("busy")), Err("busy"));
+            assert!(!is_some);
+            assert_eq!(opt.get_or_try_init(&mut is_some, || Ok::<_, ()>(1)), Ok(&1));
+            assert!(is_some);
+            assert_eq!(opt.get_or_try_init(&mut is_some, || Err(())), Ok(&1));
+        }
+    }
+
+    #[test]
+    fn swap_with_option() {
+        let mut opt = UntaggedOption::some(1);
+        let mut is_some = true;
+        let mut other = None;
+        unsafe {
+            opt.swap_with_option(&mut is_some, &mut other);
+            assert!(!is_some);
+            assert_eq!(other, Some(1));
+
+            other = Some(2);
+            opt.swap_with_option(&mut is_some, &mut other);
+            assert!(is_some);
+            assert_eq!(other, None);
+            assert_eq!(opt.take(), 2);
+        }
+    }
+
+    #[test]
+    fn flatten() {
+        let nested = UntaggedOption::some(UntaggedOption::some('x'));
+        unsafe {
+            assert_eq!(nested.flatten().take(), 'x');
+        }
+        let _ = UntaggedOption::<UntaggedOption<char>>::none().flatten();
+    }
+
+    #[test]
+    fn zip_unzip() {
+        let timestamp = UntaggedOption::some(1000u64);
+        let sample = UntaggedOption::some(-3i16);
+        let (mut timestamp, mut sample) = timestamp.zip(sample).unzip();
+        unsafe {
+            assert_eq!(timestamp.take(), 1000);
+            assert_eq!(sample.take(), -3);
+        }
+
+        // Empty options stay empty; this must not read the uninitialized payloads.
+        let (_, _) = UntaggedOption::<u64>::none().zip(UntaggedOption::<i16>::none()).unzip();
+    }
+
+    #[test]
+    fn unaligned_overlay() {
+        let mut buf = [0u8; 11];
+        let slot = buf[3..].as_mut_ptr() as *mut UntaggedOption<u64>;
+        unsafe {
+            UntaggedOption::write_unaligned(slot, 0x0102_0304_0506_0708);
+            assert_eq!(UntaggedOption::read_unaligned(slot), 0x0102_0304_0506_0708);
+        }
+        assert_eq!(&buf[3..], &0x0102_0304_0506_0708u64.to_ne_bytes());
+    }
+
+    #[test]
+    fn const_take_replace() {
+        const BUILT: (u32, u32) = {
+            let mut opt = UntaggedOption::some(1);
+            let old = unsafe { opt.replace(2) };
+            (old, unsafe { opt.take() })
+        };
+        assert_eq!(BUILT, (1, 2));
+
+        const CHECKED: (u8, bool) = {
+            let mut opt = UntaggedOption::<u8, policy::Checked>::some_with_policy(1);
+            (unsafe { opt.take() }, opt.is_none())
+        };
+        assert_eq!(CHECKED, (1, true));
+    }
+
+    #[test]
+    fn layout() {
+        use core::mem::{align_of, size_of};
+        use std::string::String;
+
+        fn same_layout<T>() {
+            assert_eq!(size_of::<UntaggedOption<T>>(), size_of::<T>());
+            assert_eq!(align_of::<UntaggedOption<T>>(), align_of::<T>());
+            assert_eq!(size_of::<[UntaggedOption<T>; 3]>(), size_of::<[T; 3]>());
+        }
+
+        same_layout::<()>();
+        same_layout::<u8>();
+        same_layout::<u64>();
+        same_layout::<(u8, u32)>();
+        same_layout::<[u16; 5]>();
+        same_layout::<String>();
+        same_layout::<&str>();
+    }
+
+    #[test]
+    fn option_like_api() {
+        use core::cell::Cell;
+
+        struct DropCounter<'a>(&'a Cell<usize>, u8);
+
+        impl<'a> Drop for DropCounter<'a> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut opt = UntaggedOption::none();
+        opt.insert(DropCounter(&drops, 1)).1 += 1;
+        unsafe {
+            assert_eq!(opt.as_ref().1, 2);
+            opt.drop_in_place();
+            assert_eq!(drops.get(), 1);
+
+            let mut is_some = false;
+            opt.get_or_insert_with(&mut is_some, || DropCounter(&drops, 3));
+            assert_eq!(opt.get_or_insert_with(&mut is_some, || unreachable!()).1, 3);
+            let old = opt.replace(DropCounter(&drops, 4));
+            assert_eq!((old.1, drops.get()), (3, 1));
+            drop(old);
+            assert_eq!(opt.into_inner().1, 4);
+        }
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn in_place() {
+        let mut buf = UntaggedOption::some([0u8; 4096]);
+        let mut other = UntaggedOption::none();
+        unsafe {
+            buf.replace_with(|mut bytes| {
+                bytes[4095] = 1;
+                bytes
+            });
+            buf.swap(&mut other);
+            assert_eq!(other.as_ref()[4095], 1);
+            assert_eq!(other.take_with(|bytes| bytes.iter().map(|&b| b as u32).sum::<u32>()), 1);
+        }
+    }
+
+    #[test]
+    fn combinators_non_copy() {
+        use std::string::String;
+        use std::vec::Vec;
+
+        let mut words = UntaggedOption::<Vec<String>, policy::Checked>::none_with_policy();
+        words.write(Vec::new());
+        unsafe {
+            words.map_in_place(|words| words.push(String::from("un")));
+            words.map_in_place(|words| words.push(String::from("tagged")));
+            let joined = words.map(|words| words.concat());
+            assert!(joined.is_some());
+            let len = joined.and_then(|s| UntaggedOption::some_with_policy(s.len()));
+            assert_eq!(len.into_inner(), 8);
+        }
+    }
+
+    #[test]
+    fn option_interop() {
+        #[derive(Clone, Copy, Default)]
+        struct Sample {
+            raw: UntaggedOption<u16>,
+            valid: bool,
+        }
+
+        let mut sample = Sample::default();
+        assert!(unsafe { sample.raw.into_option(sample.valid) }.is_none());
+        sample.raw = Some(7).into();
+        sample.valid = true;
+        let copy = sample;
+        unsafe {
+            assert_eq!(copy.raw.into_option(copy.valid), Some(7));
+            assert_eq!(sample.raw.into_inner(), 7);
+            let opt = UntaggedOption::<u16>::from_option_unchecked(Some(9));
+            assert_eq!(opt.into_inner(), 9);
+        }
+    }
+
+    #[test]
+    fn raw_access() {
+        let mut opt = UntaggedOption::<[u32; 2]>::none();
+        opt.write_volatile([1, 2]);
+        unsafe {
+            (*opt.as_mut_ptr())[1] = 3;
+            assert_eq!(opt.read_volatile(), [1, 3]);
+            opt.write([4, 5]);
+            assert_eq!(opt.read(), [4, 5]);
+            assert_eq!(*opt.as_ptr(), [4, 5]);
+        }
+    }
 }
@@ -0,0 +1,134 @@
+//! A mailbox holding one pending message per priority level.
+
+use core::fmt;
+
+use error::InsertError;
+use group::OptionGroup;
+
+/// A mailbox with `N` single-message slots, one per priority level.
+///
+/// Posting a message at some priority replaces any message pending at that priority, and the
+/// receiving side always takes the most urgent pending message first. This suits passing commands
+/// from interrupt handlers to the main loop when only the most urgent pending command matters.
+///
+/// Priorities range from `0` (least urgent) to `N - 1` (most urgent). Messages are stored untagged
+/// and their presence is tracked in a single 32-bit flag word, so `N` may be at most 32. Finding
+/// the most urgent message only takes a single bit scan.
+///
+/// To share a mailbox between an interrupt handler and the main loop, put it behind a mutex or
+/// critical section.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::PriorityMailbox;
+/// # fn main() {
+/// let mut mailbox = PriorityMailbox::<&str, 4>::new();
+/// mailbox.post(1, "refresh display");
+/// mailbox.post(3, "shut down");
+/// mailbox.post(1, "refresh display again");
+///
+/// assert_eq!(mailbox.take_highest(), Some((3, "shut down")));
+/// assert_eq!(mailbox.take_highest(), Some((1, "refresh display again")));
+/// assert_eq!(mailbox.take_highest(), None);
+/// # }
+/// ```
+pub struct PriorityMailbox<T, const N: usize> {
+    slots: OptionGroup<T, N>,
+}
+
+impl<T, const N: usize> PriorityMailbox<T, N> {
+    /// Creates an empty mailbox.
+    pub const fn new() -> Self {
+        PriorityMailbox {
+            slots: OptionGroup::new(),
+        }
+    }
+
+    /// Posts `message` at `priority`, returning the message it replaces, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `priority` is not less than `N`.
+    pub fn post(&mut self, priority: usize, message: T) -> Option<T> {
+        assert!(priority < N, "priority out of range");
+        self.slots.insert(priority, message)
+    }
+
+    /// Posts `message` at `priority`, returning the message it replaces, if any.
+    ///
+    /// This is the non-panicking version of [`post`]: if `priority` is not less than `N`,
+    /// `message` is handed back in the error.
+    ///
+    /// [`post`]: #method.post
+    pub fn try_post(&mut self, priority: usize, message: T) -> Result<Option<T>, InsertError<T>> {
+        self.slots.try_insert(priority, message)
+    }
+
+    /// Removes and returns the most urgent pending message, along with its priority.
+    pub fn take_highest(&mut self) -> Option<(usize, T)> {
+        let priority = self.slots.last_present()?;
+        self.slots.remove(priority).map(|message| (priority, message))
+    }
+
+    /// Returns the most urgent pending message, along with its priority, without removing it.
+    pub fn peek_highest(&self) -> Option<(usize, &T)> {
+        let priority = self.slots.last_present()?;
+        self.slots.get(priority).map(|message| (priority, message))
+    }
+
+    /// Removes and returns the message pending at `priority`, if any.
+    pub fn take(&mut self, priority: usize) -> Option<T> {
+        self.slots.remove(priority)
+    }
+
+    /// Returns the number of pending messages.
+    pub fn len(&self) -> usize {
+        self.slots.count()
+    }
+
+    /// Returns whether no message is pending.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Writes the pending priorities to `out`, for diagnostics.
+    ///
+    /// This prints the number of pending messages, the number of priority levels and one
+    /// character per level, starting with priority 0: `x` if a message is pending at that level
+    /// and `.` if not. The messages themselves are never printed.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "PriorityMailbox len={} capacity={} pending=", self.len(), N)?;
+        ::bitmap::write_flags(out, (0..N).map(|i| self.slots.is_present(i)))
+    }
+}
+
+impl<T, const N: usize> Default for PriorityMailbox<T, N> {
+    fn default() -> Self {
+        PriorityMailbox::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_most_urgent_first() {
+        let mut mailbox = PriorityMailbox::<u8, 32>::new();
+        assert!(mailbox.is_empty());
+        assert_eq!(mailbox.post(0, 1), None);
+        assert_eq!(mailbox.post(31, 2), None);
+        assert_eq!(mailbox.post(7, 3), None);
+        assert_eq!(mailbox.post(7, 4), Some(3));
+        assert_eq!(mailbox.try_post(32, 5).map_err(InsertError::into_value), Err(5));
+        assert_eq!(mailbox.len(), 3);
+
+        assert_eq!(mailbox.peek_highest(), Some((31, &2)));
+        assert_eq!(mailbox.take_highest(), Some((31, 2)));
+        assert_eq!(mailbox.take(0), Some(1));
+        assert_eq!(mailbox.take_highest(), Some((7, 4)));
+        assert_eq!(mailbox.take_highest(), None);
+    }
+}
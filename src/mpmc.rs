@@ -0,0 +1,292 @@
+//! A bounded multi-producer multi-consumer queue.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use sync;
+use UntaggedOption;
+
+/// One cell of an [`MpmcQueue`].
+///
+/// [`MpmcQueue`]: crate::MpmcQueue
+struct Cell<T> {
+    /// Position that the cell is ready for.
+    ///
+    /// A cell at index `i` holds no value while `seq` equals the position of the next push that
+    /// will use it (`i`, `i + N`, `i + 2N`, ...), and holds a value while `seq` is one past the
+    /// position of the push that wrote it.
+    seq: AtomicUsize,
+    value: UnsafeCell<UntaggedOption<T>>,
+}
+
+/// A bounded, lock-free queue that any number of threads can push to and pop from.
+///
+/// Every cell stores its payload untagged next to an atomic sequence number, which records
+/// whether the cell is waiting for a push or a pop and for which lap around the buffer. Producers
+/// and consumers claim positions by advancing a shared tail or head counter, then publish their
+/// write or read through the cell's sequence number. This design follows Dmitry Vyukov's bounded
+/// MPMC queue, and does not allocate.
+///
+/// The capacity `N` must be a power of two.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::MpmcQueue;
+/// # fn main() {
+/// let queue = MpmcQueue::<u32, 2>::new();
+/// assert_eq!(queue.push(1), Ok(()));
+/// assert_eq!(queue.push(2), Ok(()));
+/// assert_eq!(queue.push(3), Err(3));
+///
+/// assert_eq!(queue.pop(), Some(1));
+/// assert_eq!(queue.pop(), Some(2));
+/// assert_eq!(queue.pop(), None);
+/// # }
+/// ```
+pub struct MpmcQueue<T, const N: usize> {
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    cells: [Cell<T>; N],
+}
+
+unsafe impl<T: Send, const N: usize> Sync for MpmcQueue<T, N> {}
+
+impl<T, const N: usize> MpmcQueue<T, N> {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two(), "queue capacity must be a power of two") };
+
+        let mut cells = [const { MaybeUninit::<Cell<T>>::uninit() }; N];
+        let mut i = 0;
+        while i < N {
+            cells[i] = MaybeUninit::new(Cell {
+                seq: AtomicUsize::new(i),
+                value: UnsafeCell::new(UntaggedOption::none()),
+            });
+            i += 1;
+        }
+
+        MpmcQueue {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            // Safe: all cells were initialized above.
+            cells: unsafe { ptr::read(&cells as *const _ as *const [Cell<T>; N]) },
+        }
+    }
+
+    /// Returns the maximum number of values the queue can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of queued values.
+    ///
+    /// If other threads access the queue concurrently, the result may be out of date by the time
+    /// it is returned.
+    pub fn len(&self) -> usize {
+        loop {
+            let tail = self.tail.load(Ordering::SeqCst);
+            let head = self.head.load(Ordering::SeqCst);
+            if self.tail.load(Ordering::SeqCst) == tail {
+                return tail.wrapping_sub(head);
+            }
+        }
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Writes the length and capacity of the queue to `out`, for diagnostics.
+    ///
+    /// If other threads access the queue concurrently, the length may be out of date.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "MpmcQueue len={} capacity={}", self.len(), N)
+    }
+
+    /// Appends `value` to the back of the queue.
+    ///
+    /// If the queue is full, `value` is handed back as an error.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let cell = &self.cells[pos & (N - 1)];
+            let seq = cell.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos) as isize;
+
+            if diff == 0 {
+                match sync::compare_exchange_usize(
+                    &self.tail,
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // Safe: claiming `pos` gives this thread exclusive access to the cell until
+                        // it publishes the value below.
+                        unsafe {
+                            *cell.value.get() = UntaggedOption::some(value);
+                        }
+                        cell.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // The cell still holds the value from the previous lap.
+                return Err(value);
+            } else {
+                // Another producer claimed `pos` before us.
+                pos = self.tail.load(Ordering::Relaxed);
+                hint::spin_loop();
+            }
+        }
+    }
+
+    /// Removes the value at the front of the queue, or returns `None` if the queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.head.load(Ordering::Relaxed);
+        loop {
+            let cell = &self.cells[pos & (N - 1)];
+            let seq = cell.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+
+            if diff == 0 {
+                match sync::compare_exchange_usize(
+                    &self.head,
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // Safe: the producer published a value for `pos`, and claiming `pos`
+                        // gives this thread exclusive access to it.
+                        let value = unsafe { (*cell.value.get()).take() };
+                        cell.seq.store(pos.wrapping_add(N), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // No value has been published for `pos` yet.
+                return None;
+            } else {
+                // Another consumer claimed `pos` before us.
+                pos = self.head.load(Ordering::Relaxed);
+                hint::spin_loop();
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for MpmcQueue<T, N> {
+    fn default() -> Self {
+        MpmcQueue::new()
+    }
+}
+
+impl<T, const N: usize> Drop for MpmcQueue<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn fifo_and_wraparound() {
+        let queue = MpmcQueue::<usize, 4>::new();
+        for lap in 0..10 {
+            for i in 0..4 {
+                assert_eq!(queue.push(lap * 4 + i), Ok(()));
+            }
+            assert!(queue.is_full());
+            assert_eq!(queue.push(99), Err(99));
+            for i in 0..4 {
+                assert_eq!(queue.pop(), Some(lap * 4 + i));
+            }
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn static_queue() {
+        static QUEUE: MpmcQueue<u8, 2> = MpmcQueue::new();
+        QUEUE.push(1).unwrap();
+        assert_eq!(QUEUE.pop(), Some(1));
+    }
+
+    #[test]
+    fn drops_queued_values() {
+        let rc = Rc::new(());
+        {
+            let queue = MpmcQueue::<_, 8>::new();
+            for _ in 0..5 {
+                queue.push(rc.clone()).unwrap();
+            }
+            drop(queue.pop());
+            assert_eq!(Rc::strong_count(&rc), 5);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn concurrent_producers_and_consumers() {
+        const PER_PRODUCER: usize = 5_000;
+        let queue = MpmcQueue::<usize, 16>::new();
+        let popped = AtomicUsize::new(0);
+        let mut received = thread::scope(|s| {
+            for p in 0..3 {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        let mut value = p * PER_PRODUCER + i;
+                        while let Err(v) = queue.push(value) {
+                            value = v;
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+            let consumers: Vec<_> = (0..2)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut received = Vec::new();
+                        while popped.load(Ordering::Relaxed) < 3 * PER_PRODUCER {
+                            match queue.pop() {
+                                Some(value) => {
+                                    popped.fetch_add(1, Ordering::Relaxed);
+                                    received.push(value);
+                                }
+                                None => thread::yield_now(),
+                            }
+                        }
+                        received
+                    })
+                })
+                .collect();
+            consumers.into_iter().flat_map(|c| c.join().unwrap()).collect::<Vec<_>>()
+        });
+        received.sort();
+        assert_eq!(received, (0..3 * PER_PRODUCER).collect::<Vec<_>>());
+    }
+}
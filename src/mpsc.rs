@@ -0,0 +1,322 @@
+//! A bounded multi-producer single-consumer queue.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use sync;
+use UntaggedOption;
+
+/// A bounded queue that any number of producers can push to and a single consumer pops from.
+///
+/// The queue is meant for passing values from several interrupt handlers, which may preempt each
+/// other at different priorities, to one consumer such as the main loop. Pushing uses a
+/// claim-then-publish protocol:
+///
+/// 1. The producer claims a slot by advancing the shared tail counter with a compare-and-swap,
+///    failing if the queue is full.
+/// 2. It writes its value into the claimed slot, which no one else can access.
+/// 3. It publishes the value by setting the slot's ready flag.
+///
+/// A producer preempted between claiming and publishing never blocks the preempting one, which
+/// just claims the next slot. The consumer pops values in the order their slots were claimed, and
+/// stops at a slot that is claimed but not yet published until its producer resumes.
+///
+/// The capacity `N` must be a power of two, so that slot indices stay consistent when the
+/// position counters wrap around.
+///
+/// Use [`split`] to obtain a [`Producer`] that can be copied into every interrupt handler and the
+/// single [`Consumer`].
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::MpscQueue;
+/// # fn main() {
+/// let mut queue = MpscQueue::<u8, 4>::new();
+/// let (producer, mut consumer) = queue.split();
+///
+/// let other = producer;
+/// producer.push(1).unwrap();
+/// other.push(2).unwrap();
+///
+/// assert_eq!(consumer.pop(), Some(1));
+/// assert_eq!(consumer.pop(), Some(2));
+/// assert_eq!(consumer.pop(), None);
+/// # }
+/// ```
+///
+/// [`split`]: #method.split
+/// [`Producer`]: crate::mpsc::Producer
+/// [`Consumer`]: crate::mpsc::Consumer
+pub struct MpscQueue<T, const N: usize> {
+    /// Position of the next slot the consumer will pop. Only written by the consumer.
+    head: AtomicUsize,
+    /// Position of the next slot producers will claim.
+    tail: AtomicUsize,
+    ready: [AtomicBool; N],
+    slots: [UnsafeCell<UntaggedOption<T>>; N],
+}
+
+unsafe impl<T: Send, const N: usize> Sync for MpscQueue<T, N> {}
+
+impl<T, const N: usize> MpscQueue<T, N> {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two(), "queue capacity must be a power of two") };
+
+        MpscQueue {
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            ready: [const { AtomicBool::new(false) }; N],
+            slots: [const { UnsafeCell::new(UntaggedOption::none()) }; N],
+        }
+    }
+
+    /// Returns the maximum number of values the queue can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of claimed slots, including ones that are not published yet.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head)
+    }
+
+    /// Returns whether no slot is claimed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the state of the queue to `out`, for diagnostics.
+    ///
+    /// This prints the number of claimed slots, the capacity and one character per slot: `x` for a
+    /// slot holding a published value and `.` for any other slot. If the queue is accessed
+    /// concurrently, the output may be out of date.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "MpscQueue len={} capacity={} ready=", self.len(), N)?;
+        ::bitmap::write_flags(out, self.ready.iter().map(|ready| ready.load(Ordering::Relaxed)))
+    }
+
+    /// Splits the queue into a producer handle and the consumer.
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        (Producer { queue: self }, Consumer { queue: self, _not_sync: PhantomData })
+    }
+
+    /// Returns a producer handle.
+    ///
+    /// Any number of producers may exist at the same time.
+    pub fn producer(&self) -> Producer<'_, T, N> {
+        Producer { queue: self }
+    }
+
+    /// Returns a consumer without checking that it is the only one.
+    ///
+    /// This allows using a queue stored in a `static`, where [`split`] cannot be called.
+    ///
+    /// # Safety
+    ///
+    /// No other consumer of this queue may be used while the returned one is alive.
+    ///
+    /// [`split`]: #method.split
+    pub unsafe fn consumer_unchecked(&self) -> Consumer<'_, T, N> {
+        Consumer { queue: self, _not_sync: PhantomData }
+    }
+
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if tail.wrapping_sub(head) == N {
+                return Err(value);
+            }
+            match sync::compare_exchange_usize(
+                &self.tail,
+                tail,
+                tail.wrapping_add(1),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => tail = current,
+            }
+        }
+
+        let index = tail & (N - 1);
+        // Safe: the slot was claimed above, and the consumer has finished with it because `head`
+        // has moved past its previous use.
+        unsafe {
+            *self.slots[index].get() = UntaggedOption::some(value);
+        }
+        self.ready[index].store(true, Ordering::Release);
+        Ok(())
+    }
+
+    fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let index = head & (N - 1);
+        if !self.ready[index].load(Ordering::Acquire) {
+            return None;
+        }
+
+        // Safe: the ready flag is set, so the producer has published the value and won't touch
+        // the slot again.
+        let value = unsafe { (*self.slots[index].get()).take() };
+        self.ready[index].store(false, Ordering::Relaxed);
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T, const N: usize> Default for MpscQueue<T, N> {
+    fn default() -> Self {
+        MpscQueue::new()
+    }
+}
+
+impl<T, const N: usize> Drop for MpscQueue<T, N> {
+    fn drop(&mut self) {
+        // No producer can be halfway through a push here, so every claimed slot is published.
+        while self.pop().is_some() {}
+    }
+}
+
+/// A handle for pushing values into an [`MpscQueue`].
+///
+/// Producers are `Copy`, so one can be handed to every interrupt handler.
+///
+/// [`MpscQueue`]: crate::MpscQueue
+pub struct Producer<'a, T: 'a, const N: usize> {
+    queue: &'a MpscQueue<T, N>,
+}
+
+impl<'a, T, const N: usize> Producer<'a, T, N> {
+    /// Appends `value` to the back of the queue.
+    ///
+    /// If the queue is full, `value` is handed back as an error.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        self.queue.push(value)
+    }
+}
+
+impl<'a, T, const N: usize> Clone for Producer<'a, T, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, const N: usize> Copy for Producer<'a, T, N> {}
+
+/// The consuming end of an [`MpscQueue`].
+///
+/// [`MpscQueue`]: crate::MpscQueue
+pub struct Consumer<'a, T: 'a, const N: usize> {
+    queue: &'a MpscQueue<T, N>,
+    /// A consumer may be sent to another thread, but not shared with one.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<'a, T, const N: usize> Consumer<'a, T, N> {
+    /// Removes the value at the front of the queue.
+    ///
+    /// Returns `None` if the queue is empty, or if the front slot is claimed by a producer that
+    /// has not published its value yet.
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn claim_order_and_wraparound() {
+        let mut queue = MpscQueue::<usize, 4>::new();
+        let (producer, mut consumer) = queue.split();
+        for lap in 0..5 {
+            for i in 0..4 {
+                assert_eq!(producer.push(lap * 4 + i), Ok(()));
+            }
+            assert_eq!(producer.push(99), Err(99));
+            for i in 0..4 {
+                assert_eq!(consumer.pop(), Some(lap * 4 + i));
+            }
+            assert_eq!(consumer.pop(), None);
+        }
+    }
+
+    #[test]
+    fn static_queue() {
+        static QUEUE: MpscQueue<u8, 2> = MpscQueue::new();
+        QUEUE.producer().push(1).unwrap();
+        let mut consumer = unsafe { QUEUE.consumer_unchecked() };
+        assert_eq!(consumer.pop(), Some(1));
+    }
+
+    #[test]
+    fn dump() {
+        let mut queue = MpscQueue::<u8, 4>::new();
+        let (producer, mut consumer) = queue.split();
+        producer.push(1).unwrap();
+        producer.push(2).unwrap();
+        consumer.pop();
+        let mut out = String::new();
+        queue.dump(&mut out).unwrap();
+        assert_eq!(out, "MpscQueue len=1 capacity=4 ready=.x..");
+    }
+
+    #[test]
+    fn drops_queued_values() {
+        let rc = Rc::new(());
+        {
+            let queue = MpscQueue::<_, 4>::new();
+            queue.producer().push(rc.clone()).unwrap();
+            queue.producer().push(rc.clone()).unwrap();
+            assert_eq!(Rc::strong_count(&rc), 3);
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn concurrent_producers() {
+        const PER_PRODUCER: usize = 5_000;
+        let mut queue = MpscQueue::<usize, 8>::new();
+        let (producer, mut consumer) = queue.split();
+        thread::scope(|s| {
+            for p in 0..3 {
+                s.spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        let mut value = p * PER_PRODUCER + i;
+                        while let Err(v) = producer.push(value) {
+                            value = v;
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+
+            // Values of each producer arrive in the order they were pushed.
+            let mut next = [0, PER_PRODUCER, 2 * PER_PRODUCER];
+            let mut received = Vec::new();
+            while received.len() < 3 * PER_PRODUCER {
+                match consumer.pop() {
+                    Some(value) => {
+                        let p = value / PER_PRODUCER;
+                        assert_eq!(value, next[p]);
+                        next[p] += 1;
+                        received.push(value);
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+    }
+}
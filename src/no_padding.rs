@@ -0,0 +1,34 @@
+//! Marker for payloads whose storage can be read byte by byte.
+
+/// Types without padding or other uninitialized bytes.
+///
+/// Some parts of the crate access a payload as raw bytes: [`VersionedSlot`] copies it with
+/// byte-sized atomics, and [`as_ref_validated`] scans it for the poison pattern. Reading an
+/// uninitialized byte is undefined behavior, so those APIs require this trait.
+///
+/// It is implemented for the primitive integer and floating-point types, `bool`, `char`, `()` and
+/// arrays of such types. Tuples and structs are not covered even if their fields are, since the
+/// compiler may insert padding between the fields: `(u8, u32)` has three padding bytes.
+///
+/// It can be implemented for `#[repr(C)]` structs whose fields leave no gaps, and for types that
+/// implement `bytemuck::NoUninit`, which has the same requirements.
+///
+/// # Safety
+///
+/// Every byte of every valid value of the type must be initialized. Pointers and references must
+/// not implement this trait either, since copying them as bytes loses their provenance.
+///
+/// [`VersionedSlot`]: crate::VersionedSlot
+/// [`as_ref_validated`]: crate::UntaggedOption::as_ref_validated
+pub unsafe trait NoPadding {}
+
+macro_rules! impl_no_padding {
+    ($($t:ty),*) => {
+        $(unsafe impl NoPadding for $t {})*
+    };
+}
+
+impl_no_padding!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+impl_no_padding!(bool, char, ());
+
+unsafe impl<T: NoPadding, const N: usize> NoPadding for [T; N] {}
@@ -0,0 +1,209 @@
+//! A safe option that uses a reserved value of the payload type as `None`.
+//!
+//! When a type has a value that never occurs in practice, such as `u32::MAX` as an index, that
+//! value can stand in for `None`, and [`PackedOption`] recovers a fully safe `Option` API without
+//! storing anything besides the payload. Types opt in by implementing [`Reserved`].
+//!
+//! This crate implements `Reserved` for the unsigned integers and the `NonZero` integers (with
+//! their `MAX` value reserved) and for raw pointers (with null reserved). References are not
+//! covered: every value of a `&T` is a valid reference, and `Option<&T>` already uses the null
+//! niche, so it is the packed option for references.
+//!
+//! [`PackedOption`]: crate::packed::PackedOption
+//! [`Reserved`]: crate::packed::Reserved
+
+use core::fmt;
+use core::mem;
+use core::num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize};
+use core::ptr;
+
+/// A type with a value that is never used, so it can represent `None` in a [`PackedOption`].
+///
+/// [`PackedOption`]: crate::packed::PackedOption
+pub trait Reserved {
+    /// Returns the reserved value.
+    fn reserved() -> Self;
+
+    /// Returns whether `self` is the reserved value.
+    fn is_reserved(&self) -> bool;
+}
+
+macro_rules! reserved_max {
+    ($($ty:ty),*) => {$(
+        impl Reserved for $ty {
+            fn reserved() -> Self {
+                <$ty>::MAX
+            }
+
+            fn is_reserved(&self) -> bool {
+                *self == <$ty>::MAX
+            }
+        }
+    )*};
+}
+
+reserved_max!(u8, u16, u32, u64, usize);
+reserved_max!(NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize);
+
+impl<T> Reserved for *const T {
+    fn reserved() -> Self {
+        ptr::null()
+    }
+
+    fn is_reserved(&self) -> bool {
+        self.is_null()
+    }
+}
+
+impl<T> Reserved for *mut T {
+    fn reserved() -> Self {
+        ptr::null_mut()
+    }
+
+    fn is_reserved(&self) -> bool {
+        self.is_null()
+    }
+}
+
+/// An option that is as large as `T` and stores `None` as the [reserved value] of `T`.
+///
+/// Unlike [`UntaggedOption`], every method is safe. The price is that the reserved value itself
+/// cannot be stored: [`some`] debug-asserts against it, and in release builds storing it yields
+/// an empty option.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::PackedOption;
+/// # fn main() {
+/// let mut parent = PackedOption::<u32>::none();
+/// assert!(parent.is_none());
+/// parent = Some(4).into();
+/// assert_eq!(parent.take(), Some(4));
+/// assert_eq!(parent.expand(), None);
+/// assert_eq!(std::mem::size_of_val(&parent), 4);
+/// # }
+/// ```
+///
+/// [reserved value]: crate::packed::Reserved
+/// [`UntaggedOption`]: crate::UntaggedOption
+/// [`some`]: #method.some
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct PackedOption<T: Reserved>(T);
+
+impl<T: Reserved> PackedOption<T> {
+    /// Creates an empty `PackedOption`.
+    pub fn none() -> Self {
+        PackedOption(T::reserved())
+    }
+
+    /// Creates a `PackedOption` holding `value`.
+    ///
+    /// `value` should not be the reserved value; that is checked in debug builds.
+    pub fn some(value: T) -> Self {
+        debug_assert!(!value.is_reserved(), "stored the reserved value in a `PackedOption`");
+        PackedOption(value)
+    }
+
+    /// Returns whether `self` holds a value.
+    pub fn is_some(&self) -> bool {
+        !self.0.is_reserved()
+    }
+
+    /// Returns whether `self` is empty.
+    pub fn is_none(&self) -> bool {
+        self.0.is_reserved()
+    }
+
+    /// Takes the value out of `self`, leaving it empty.
+    pub fn take(&mut self) -> Option<T> {
+        mem::take(self).expand()
+    }
+
+    /// Stores `value` in `self`, returning the previous value, if any.
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        mem::replace(self, PackedOption::some(value)).expand()
+    }
+
+    /// Converts `self` into a regular `Option`.
+    pub fn expand(self) -> Option<T> {
+        if self.is_some() {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the contained value, if any.
+    pub fn as_ref(&self) -> Option<&T> {
+        if self.is_some() {
+            Some(&self.0)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the contained value, if any.
+    pub fn as_mut(&mut self) -> Option<&mut T> {
+        if self.is_some() {
+            Some(&mut self.0)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Reserved> Default for PackedOption<T> {
+    fn default() -> Self {
+        PackedOption::none()
+    }
+}
+
+impl<T: Reserved> From<Option<T>> for PackedOption<T> {
+    fn from(opt: Option<T>) -> Self {
+        match opt {
+            Some(value) => PackedOption::some(value),
+            None => PackedOption::none(),
+        }
+    }
+}
+
+impl<T: Reserved> From<PackedOption<T>> for Option<T> {
+    fn from(opt: PackedOption<T>) -> Self {
+        opt.expand()
+    }
+}
+
+impl<T: Reserved + fmt::Debug> fmt::Debug for PackedOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::format;
+
+    #[test]
+    fn option_api() {
+        let mut opt = PackedOption::some(NonZeroU8::new(3).unwrap());
+        assert_eq!(mem::size_of_val(&opt), 1);
+        assert_eq!(opt.replace(NonZeroU8::MIN).map(NonZeroU8::get), Some(3));
+        assert_eq!(format!("{:?}", opt), "Some(1)");
+        assert_eq!(opt.take(), Some(NonZeroU8::MIN));
+        assert!(opt.is_none());
+        assert_eq!(format!("{:?}", opt), "None");
+        assert_eq!(PackedOption::<u16>::default(), PackedOption::from(None));
+    }
+
+    #[test]
+    fn pointers() {
+        let x = 5;
+        let opt = PackedOption::from(Some(&x as *const i32));
+        assert!(opt.is_some());
+        assert!(PackedOption::<*mut i32>::none().expand().is_none());
+    }
+}
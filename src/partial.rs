@@ -0,0 +1,156 @@
+//! Incremental initialization of arrays.
+
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::slice;
+
+use policy::Unchecked;
+use UntaggedOption;
+
+/// An array of `N` elements that is filled front to back.
+///
+/// Elements are appended with [`push`], and the initialized prefix can be inspected with
+/// [`as_slice`]. Once all `N` elements are present, the array can be taken out with
+/// [`into_array`]. Dropping a `PartialArray` drops only the initialized prefix.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::PartialArray;
+/// # fn main() {
+/// let mut coeffs = PartialArray::<i16, 3>::new();
+/// for c in [4, -2, 7] {
+///     coeffs.push(c).unwrap();
+/// }
+/// assert_eq!(coeffs.as_slice(), [4, -2, 7]);
+/// assert_eq!(coeffs.into_array().ok(), Some([4, -2, 7]));
+/// # }
+/// ```
+///
+/// [`push`]: #method.push
+/// [`as_slice`]: #method.as_slice
+/// [`into_array`]: #method.into_array
+pub struct PartialArray<T, const N: usize> {
+    /// Always `Unchecked`, since `as_slice` and `into_array` rely on the slots having the layout
+    /// of `T`.
+    slots: [UntaggedOption<T, Unchecked>; N],
+    /// The number of initialized elements at the start of `slots`.
+    len: usize,
+}
+
+impl<T, const N: usize> PartialArray<T, N> {
+    /// Creates an empty `PartialArray`.
+    pub const fn new() -> Self {
+        PartialArray {
+            slots: [const { UntaggedOption::none() }; N],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of initialized elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no element has been initialized yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns whether all `N` elements have been initialized.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Appends `value` after the initialized prefix.
+    ///
+    /// If all elements are already initialized, `value` is handed back.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        match self.slots.get_mut(self.len) {
+            Some(slot) => {
+                slot.write(value);
+                self.len += 1;
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Returns the initialized prefix.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.slots.as_ptr() as *const T, self.len) }
+    }
+
+    /// Returns the initialized prefix mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.slots.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Returns the array if all elements are initialized, or `self` otherwise.
+    pub fn into_array(self) -> Result<[T; N], Self> {
+        if self.is_full() {
+            Ok(unsafe { self.assume_init() })
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Returns the array without checking that all elements are initialized.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that all `N` elements have been initialized, that is, that
+    /// [`is_full`] returns `true`.
+    ///
+    /// [`is_full`]: #method.is_full
+    pub unsafe fn assume_init(self) -> [T; N] {
+        let this = ManuallyDrop::new(self);
+        ptr::read(this.slots.as_ptr() as *const [T; N])
+    }
+}
+
+impl<T, const N: usize> Default for PartialArray<T, N> {
+    fn default() -> Self {
+        PartialArray::new()
+    }
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        for slot in &mut self.slots[..self.len] {
+            unsafe { slot.drop_in_place() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn drops_prefix() {
+        let rc = Rc::new(());
+        let mut arr = PartialArray::<Rc<()>, 4>::new();
+        arr.push(rc.clone()).unwrap();
+        arr.push(rc.clone()).unwrap();
+        assert_eq!(arr.len(), 2);
+        assert_eq!(Rc::strong_count(&arr.as_slice()[1]), 3);
+        let arr = arr.into_array().unwrap_err();
+        drop(arr);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn fill() {
+        let mut arr = PartialArray::<u8, 2>::default();
+        assert!(arr.is_empty());
+        arr.push(1).unwrap();
+        arr.push(2).unwrap();
+        assert_eq!(arr.push(3), Err(3));
+        arr.as_mut_slice()[0] = 5;
+        assert!(arr.is_full());
+        assert_eq!(unsafe { arr.assume_init() }, [5, 2]);
+    }
+}
@@ -0,0 +1,139 @@
+//! Pinning support.
+//!
+//! `UntaggedOption<T>` is `Unpin` exactly when `T` is, so it can be used as a structurally pinned
+//! field, for example with `pin-project-lite`. The projection helpers here then turn a pinned
+//! reference to the option into a pinned reference to its payload.
+//!
+//! Pinning the payload through these helpers is a promise that holds until the payload is
+//! dropped: once a pinned reference to it has been created, the payload must not be moved out
+//! (so [`take`] and [`replace`] are off limits unless `T` is `Unpin`), and it must be dropped in
+//! place with [`drop_pinned`] before the option is emptied, overwritten or freed. [`set_pinned`]
+//! starts a new payload in an empty, pinned option.
+//!
+//! [`take`]: crate::UntaggedOption::take
+//! [`replace`]: crate::UntaggedOption::replace
+//! [`drop_pinned`]: crate::UntaggedOption::drop_pinned
+//! [`set_pinned`]: crate::UntaggedOption::set_pinned
+
+use core::pin::Pin;
+
+use UntaggedOption;
+
+impl<T> UntaggedOption<T> {
+    /// Obtains a pinned immutable reference to the contained `T`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn as_pin_ref(self: Pin<&Self>) -> Pin<&T> {
+        self.map_unchecked(|opt| opt.as_ref())
+    }
+
+    /// Obtains a pinned mutable reference to the contained `T`.
+    ///
+    /// Once the payload has been pinned, it must not be moved out with [`take`] (unless `T` is
+    /// `Unpin`), and its destructor must run before the option's memory is reused or freed.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    ///
+    /// [`take`]: #method.take
+    pub unsafe fn as_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+        self.map_unchecked_mut(|opt| opt.as_mut())
+    }
+
+    /// Stores `value` in a pinned option and returns a pinned reference to it.
+    ///
+    /// # Safety
+    ///
+    /// `self` must not hold a value whose destructor has not run yet, since that value would be
+    /// overwritten in place.
+    pub unsafe fn set_pinned(self: Pin<&mut Self>, value: T) -> Pin<&mut T> {
+        self.map_unchecked_mut(|opt| opt.insert(value))
+    }
+
+    /// Drops the pinned payload in place, leaving `self` empty.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn drop_pinned(self: Pin<&mut Self>) {
+        self.get_unchecked_mut().drop_in_place();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::Future;
+    use core::marker::PhantomPinned;
+    use core::ptr;
+    use core::task::{Context, Poll, Waker};
+
+    /// A future that must not move between polls.
+    struct SelfAware {
+        addr: *const SelfAware,
+        _pinned: PhantomPinned,
+    }
+
+    impl Drop for SelfAware {
+        fn drop(&mut self) {
+            // Must be dropped where it was polled.
+            assert!(self.addr.is_null() || ptr::eq(self.addr, self));
+        }
+    }
+
+    impl Future for SelfAware {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+            let this = unsafe { self.get_unchecked_mut() };
+            if this.addr.is_null() {
+                this.addr = this;
+                Poll::Pending
+            } else {
+                assert!(ptr::eq(this.addr, this));
+                Poll::Ready(())
+            }
+        }
+    }
+
+    pin_project! {
+        struct Task {
+            #[pin]
+            fut: UntaggedOption<SelfAware>,
+            polls: u8,
+        }
+    }
+
+    impl Task {
+        fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            let this = self.project();
+            *this.polls += 1;
+            unsafe { this.fut.as_pin_mut().poll(cx) }
+        }
+    }
+
+    #[test]
+    fn structural_pinning() {
+        let mut task = Task {
+            fut: UntaggedOption::none(),
+            polls: 0,
+        };
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut task = unsafe { Pin::new_unchecked(&mut task) };
+        let fut = SelfAware {
+            addr: ptr::null(),
+            _pinned: PhantomPinned,
+        };
+        unsafe { task.as_mut().project().fut.set_pinned(fut) };
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Ready(()));
+        assert_eq!(task.polls, 2);
+        unsafe {
+            assert!(!task.as_ref().project_ref().fut.as_pin_ref().addr.is_null());
+            task.as_mut().project().fut.drop_pinned();
+        }
+    }
+}
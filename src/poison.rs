@@ -0,0 +1,197 @@
+//! Poisoning of empty slots, for catching use-after-take and missed initialization.
+//!
+//! With the `poison` feature enabled, [`UntaggedOption::none`] fills the storage of the new
+//! option with [`POISON_BYTE`], and [`UntaggedOption::take`] and
+//! [`UntaggedOption::drop_in_place`] overwrite the storage of the removed value with it. Reading
+//! an empty option then yields a recognizable bit pattern instead of stale data, and
+//! [`UntaggedOption::validate`] can check at subsystem boundaries whether an option that is
+//! believed to hold a value still holds the poison pattern.
+//!
+//! [`UntaggedOption::as_ref_validated`] and [`UntaggedOption::as_mut_validated`] perform that check
+//! on every access and panic when they find the pattern, which catches accesses after `take`. The
+//! check reads the storage byte by byte, which is only defined if all of its bytes are initialized.
+//! Padding bytes of a valid `T` are not, so these accessors require `T: NoPadding`, and the plain
+//! [`UntaggedOption::as_ref`] and [`UntaggedOption::as_mut`] never check. That keeps the safety
+//! contract of the plain accessors the same with and without the `poison` feature.
+//!
+//! Individual options can be poisoned regardless of the feature by using the [`Poison`] policy.
+//!
+//! [`UntaggedOption::none`]: crate::UntaggedOption::none
+//! [`UntaggedOption::take`]: crate::UntaggedOption::take
+//! [`UntaggedOption::drop_in_place`]: crate::UntaggedOption::drop_in_place
+//! [`UntaggedOption::validate`]: crate::UntaggedOption::validate
+//! [`UntaggedOption::as_ref_validated`]: crate::UntaggedOption::as_ref_validated
+//! [`UntaggedOption::as_mut_validated`]: crate::UntaggedOption::as_mut_validated
+//! [`UntaggedOption::as_ref`]: crate::UntaggedOption::as_ref
+//! [`UntaggedOption::as_mut`]: crate::UntaggedOption::as_mut
+//! [`POISON_BYTE`]: crate::poison::POISON_BYTE
+//! [`Poison`]: crate::policy::Poison
+
+use core::error::Error;
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+use no_padding::NoPadding;
+use policy::Policy;
+use UntaggedOption;
+
+/// The byte that the storage of empty options is filled with.
+pub const POISON_BYTE: u8 = 0xDE;
+
+/// Error returned by [`UntaggedOption::validate`] when the storage holds the poison pattern.
+///
+/// [`UntaggedOption::validate`]: crate::UntaggedOption::validate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoisonDetected;
+
+impl fmt::Display for PoisonDetected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("option holds the poison pattern")
+    }
+}
+
+impl Error for PoisonDetected {}
+
+/// Fills the storage of `opt` with `POISON_BYTE`.
+pub(crate) const fn poison<T, P: Policy>(opt: &mut UntaggedOption<T, P>) {
+    unsafe {
+        ptr::write_bytes(opt.as_mut_ptr() as *mut u8, POISON_BYTE, mem::size_of::<T>());
+    }
+}
+
+impl<T, P: Policy> UntaggedOption<T, P> {
+    /// Checks that `self` does not hold the poison pattern.
+    ///
+    /// Returns `Err(PoisonDetected)` if every byte of the storage equals [`POISON_BYTE`], which
+    /// indicates that the value was taken out of `self` or was never stored. Options of zero-sized
+    /// types are never reported.
+    ///
+    /// A valid value can consist of the poison pattern by coincidence, so a detection is a strong
+    /// hint but not a proof of a bug.
+    ///
+    /// # Safety
+    ///
+    /// Every byte of the storage is read, so `self` must either be empty or hold a valid `T`
+    /// without padding bytes, which might be uninitialized.
+    ///
+    /// [`POISON_BYTE`]: crate::poison::POISON_BYTE
+    pub unsafe fn validate(&self) -> Result<(), PoisonDetected> {
+        let bytes = self.as_ptr() as *const u8;
+        let size = mem::size_of::<T>();
+        if size > 0 && (0..size).all(|i| ptr::read_volatile(bytes.add(i)) == POISON_BYTE) {
+            Err(PoisonDetected)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: NoPadding, P: Policy> UntaggedOption<T, P> {
+    /// Obtains an immutable reference to the contained `T`, checking for the poison pattern.
+    ///
+    /// This is [`as_ref`] with an additional [`validate`] call if the policy poisons empty options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy poisons empty options and `self` holds the poison pattern. A valid
+    /// value that consists of [`POISON_BYTE`] also panics.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`as_ref`]: #method.as_ref
+    /// [`validate`]: #method.validate
+    /// [`POISON_BYTE`]: crate::poison::POISON_BYTE
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn as_ref_validated(&self) -> &T {
+        self.assert_not_poisoned();
+        self.as_ref()
+    }
+
+    /// Obtains a mutable reference to the contained `T`, checking for the poison pattern.
+    ///
+    /// This is [`as_mut`] with an additional [`validate`] call if the policy poisons empty options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy poisons empty options and `self` holds the poison pattern. A valid
+    /// value that consists of [`POISON_BYTE`] also panics.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`as_mut`]: #method.as_mut
+    /// [`validate`]: #method.validate
+    /// [`POISON_BYTE`]: crate::poison::POISON_BYTE
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn as_mut_validated(&mut self) -> &mut T {
+        self.assert_not_poisoned();
+        self.as_mut()
+    }
+
+    /// Panics if the policy poisons empty options and `self` holds the poison pattern.
+    unsafe fn assert_not_poisoned(&self) {
+        if Self::is_poisoning() && self.validate().is_err() {
+            panic!("accessed a poisoned UntaggedOption");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "poison"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poisoned_after_take() {
+        let mut opt = UntaggedOption::<u64>::none();
+        unsafe {
+            assert_eq!(opt.validate(), Err(PoisonDetected));
+            opt = UntaggedOption::some(1);
+            assert_eq!(opt.validate(), Ok(()));
+            assert_eq!(opt.take(), 1);
+            assert_eq!(opt.validate(), Err(PoisonDetected));
+            assert_eq!(*opt.as_ptr(), 0xDEDE_DEDE_DEDE_DEDE);
+        }
+    }
+
+    #[test]
+    fn poisoned_after_drop() {
+        use std::rc::Rc;
+
+        let rc = Rc::new(5u8);
+        let mut opt = UntaggedOption::some(rc.clone());
+        unsafe {
+            assert_eq!(**opt.as_ref(), 5);
+            opt.drop_in_place();
+            assert_eq!(opt.validate(), Err(PoisonDetected));
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn validated_access_after_take_panics() {
+        use std::panic;
+
+        let mut opt = UntaggedOption::some(3u32);
+        unsafe {
+            *opt.as_mut_validated() += 1;
+            assert_eq!(opt.take(), 4);
+        }
+        let result = panic::catch_unwind(move || unsafe { *opt.as_ref_validated() });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn statics_are_poisoned() {
+        static OPT: UntaggedOption<[u8; 3]> = UntaggedOption::none();
+        unsafe {
+            assert_eq!(OPT.validate(), Err(PoisonDetected));
+            assert_eq!(UntaggedOption::<()>::none().validate(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Per-slot policies that add checks or instrumentation to an `UntaggedOption`.
+//!
+//! The policy is the second type parameter of [`UntaggedOption`]. The default, [`Unchecked`],
+//! adds nothing. The others store a little state next to the payload, and are meant for auditing
+//! individual slots while keeping all others raw:
+//!
+//! * [`Checked`] stores a presence tag and panics when an empty option is accessed.
+//! * [`Poison`] fills the storage of empty options with the [poison pattern], independently of
+//!   the crate-wide `poison` feature.
+//! * [`Counting`] counts the accesses to the payload.
+//!
+//! [`DebugChecked`] selects `Checked` in builds with debug assertions and `Unchecked` otherwise,
+//! for catching misuse during development without paying for the tag in release builds.
+//!
+//! Options with a non-default policy must be created with [`none_with_policy`] and
+//! [`some_with_policy`], since the `none` and `some` constructors always use `Unchecked`.
+//!
+//! ```
+//! # extern crate untagged_option;
+//! # use untagged_option::UntaggedOption;
+//! # use untagged_option::policy::Checked;
+//! # fn main() {
+//! let mut opt = UntaggedOption::<u8, Checked>::some_with_policy(1);
+//! assert!(opt.is_some());
+//! unsafe { opt.take(); }
+//! assert!(!opt.is_some());
+//! # }
+//! ```
+//!
+//! [`UntaggedOption`]: crate::UntaggedOption
+//! [`Unchecked`]: crate::policy::Unchecked
+//! [`Checked`]: crate::policy::Checked
+//! [`Poison`]: crate::policy::Poison
+//! [`Counting`]: crate::policy::Counting
+//! [`DebugChecked`]: crate::policy::DebugChecked
+//! [poison pattern]: crate::poison
+//! [`none_with_policy`]: crate::UntaggedOption::none_with_policy
+//! [`some_with_policy`]: crate::UntaggedOption::some_with_policy
+
+use core::cell::Cell;
+
+use UntaggedOption;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Identifies a policy, so that the hooks can be dispatched in `const fn`s.
+#[doc(hidden)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Unchecked,
+    Checked,
+    Poison,
+    Counting,
+}
+
+/// A policy for an [`UntaggedOption`].
+///
+/// This trait is sealed and implemented by the policies in the [`policy`] module.
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+/// [`policy`]: crate::policy
+pub trait Policy: sealed::Sealed {
+    /// The state stored next to the payload of every option using this policy.
+    type State;
+
+    #[doc(hidden)]
+    const KIND: Kind;
+    #[doc(hidden)]
+    const EMPTY: Self::State;
+    #[doc(hidden)]
+    const FULL: Self::State;
+}
+
+/// The default policy, which stores no state and performs no checks.
+///
+/// With the `poison` feature enabled, empty options are poisoned like with [`Poison`].
+///
+/// [`Poison`]: crate::policy::Poison
+#[derive(Debug)]
+pub enum Unchecked {}
+
+/// A policy that tracks presence in a tag and panics when an empty option is accessed.
+///
+/// The tag is only updated by the methods of `UntaggedOption`, so it is accurate as long as the
+/// option is filled and emptied through them. It does not make any method safe to call, and the
+/// payload is still not dropped automatically.
+#[derive(Debug)]
+pub enum Checked {}
+
+/// A policy that fills the storage of empty options with the [poison pattern].
+///
+/// [poison pattern]: crate::poison
+#[derive(Debug)]
+pub enum Poison {}
+
+/// A policy that counts how often the payload is accessed through [`as_ref`], [`as_mut`] and
+/// [`take`].
+///
+/// The count is kept in a `Cell`, so options using this policy are not `Sync`.
+///
+/// [`as_ref`]: crate::UntaggedOption::as_ref
+/// [`as_mut`]: crate::UntaggedOption::as_mut
+/// [`take`]: crate::UntaggedOption::take
+#[derive(Debug)]
+pub enum Counting {}
+
+/// [`Checked`] in builds with debug assertions, [`Unchecked`] otherwise.
+///
+/// Options using this policy panic on misuse during development and have the same layout as their
+/// payload in release builds. Since only `Checked` options know whether they hold a value, code
+/// using this policy cannot call `is_some` or `is_none`.
+///
+/// [`Checked`]: crate::policy::Checked
+/// [`Unchecked`]: crate::policy::Unchecked
+#[cfg(debug_assertions)]
+pub type DebugChecked = Checked;
+
+/// [`Checked`] in builds with debug assertions, [`Unchecked`] otherwise.
+///
+/// Options using this policy panic on misuse during development and have the same layout as their
+/// payload in release builds. Since only `Checked` options know whether they hold a value, code
+/// using this policy cannot call `is_some` or `is_none`.
+///
+/// [`Checked`]: crate::policy::Checked
+/// [`Unchecked`]: crate::policy::Unchecked
+#[cfg(not(debug_assertions))]
+pub type DebugChecked = Unchecked;
+
+impl sealed::Sealed for Unchecked {}
+impl sealed::Sealed for Checked {}
+impl sealed::Sealed for Poison {}
+impl sealed::Sealed for Counting {}
+
+impl Policy for Unchecked {
+    type State = ();
+    const KIND: Kind = Kind::Unchecked;
+    const EMPTY: () = ();
+    const FULL: () = ();
+}
+
+impl Policy for Checked {
+    type State = bool;
+    const KIND: Kind = Kind::Checked;
+    const EMPTY: bool = false;
+    const FULL: bool = true;
+}
+
+impl Policy for Poison {
+    type State = ();
+    const KIND: Kind = Kind::Poison;
+    const EMPTY: () = ();
+    const FULL: () = ();
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+impl Policy for Counting {
+    type State = Cell<u32>;
+    const KIND: Kind = Kind::Counting;
+    const EMPTY: Cell<u32> = Cell::new(0);
+    const FULL: Cell<u32> = Cell::new(0);
+}
+
+impl<T> UntaggedOption<T, Checked> {
+    /// Returns whether `self` holds a value according to its presence tag.
+    pub const fn is_some(&self) -> bool {
+        self.state
+    }
+
+    /// Returns whether `self` is empty according to its presence tag.
+    pub const fn is_none(&self) -> bool {
+        !self.state
+    }
+}
+
+impl<T> UntaggedOption<T, Counting> {
+    /// Returns how often the payload was accessed since `self` was created.
+    pub fn accesses(&self) -> u32 {
+        self.state.get()
+    }
+
+    /// Resets the access count to zero.
+    pub fn reset_accesses(&self) {
+        self.state.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use poison::PoisonDetected;
+    use std::panic;
+
+    #[test]
+    fn checked_tag() {
+        let mut opt = UntaggedOption::<u32, Checked>::none_with_policy();
+        assert!(opt.is_none());
+        opt = UntaggedOption::some_with_policy(5);
+        unsafe {
+            *opt.as_mut() += 1;
+            assert_eq!(opt.take(), 6);
+        }
+        assert!(opt.is_none());
+
+        let result = panic::catch_unwind(move || unsafe { *opt.as_ref() });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn poison_and_counting() {
+        let mut opt = UntaggedOption::<u16, Poison>::some_with_policy(3);
+        unsafe {
+            assert_eq!(opt.validate(), Ok(()));
+            opt.take();
+            assert_eq!(opt.validate(), Err(PoisonDetected));
+        }
+
+        let mut opt = UntaggedOption::<u16, Counting>::some_with_policy(3);
+        unsafe {
+            assert_eq!(*opt.as_ref() + *opt.as_mut(), 6);
+            assert_eq!(opt.accesses(), 2);
+            opt.take();
+        }
+        assert_eq!(opt.accesses(), 3);
+        opt.reset_accesses();
+        assert_eq!(opt.accesses(), 0);
+    }
+
+    #[test]
+    fn unchecked_adds_no_state() {
+        assert_eq!(mem::size_of::<UntaggedOption<u32>>(), 4);
+        assert_eq!(mem::size_of::<UntaggedOption<u32, Poison>>(), 4);
+        assert_eq!(mem::size_of::<UntaggedOption<u32, Checked>>(), 8);
+    }
+
+    #[test]
+    fn debug_checked() {
+        let mut opt = UntaggedOption::<u32, DebugChecked>::some_with_policy(1);
+        assert_eq!(unsafe { opt.take() }, 1);
+        if cfg!(debug_assertions) {
+            let result = panic::catch_unwind(move || unsafe { opt.take() });
+            assert!(result.is_err());
+        } else {
+            assert_eq!(mem::size_of_val(&opt), 4);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! A pointer-sized borrowed-or-owned smart pointer.
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Deref;
+use core::ptr::NonNull;
+
+/// Tag bit set in the pointer of an owned `PtrCow`.
+const OWNED: usize = 1;
+
+/// Either a borrowed `&'a T` or an owned `Box<T>`, in a single pointer.
+///
+/// Unlike an enum of both, which takes two words, `PtrCow` stores whether it owns its pointee in
+/// the lowest bit of the pointer. This requires `T` to have an alignment of at least 2, which is
+/// checked at compile time. `Option<PtrCow<T>>` is pointer-sized as well.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::PtrCow;
+/// # fn main() {
+/// let header = 0x1234u16;
+/// let borrowed = PtrCow::borrowed(&header);
+/// let owned = PtrCow::owned(Box::new(0x5678u16));
+///
+/// assert!(!borrowed.is_owned());
+/// assert!(owned.is_owned());
+/// assert_eq!(*borrowed + *owned, 0x68ac);
+/// assert_eq!(std::mem::size_of::<PtrCow<u16>>(), std::mem::size_of::<usize>());
+/// # }
+/// ```
+pub struct PtrCow<'a, T: 'a> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<(&'a T, Box<T>)>,
+}
+
+unsafe impl<'a, T: Send + Sync> Send for PtrCow<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for PtrCow<'a, T> {}
+
+impl<'a, T> PtrCow<'a, T> {
+    /// Creates a `PtrCow` borrowing `value`.
+    pub fn borrowed(value: &'a T) -> Self {
+        const { assert!(mem::align_of::<T>() >= 2, "`PtrCow` requires an alignment of 2 or more") };
+
+        PtrCow {
+            ptr: NonNull::from(value),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a `PtrCow` owning `value`.
+    pub fn owned(value: Box<T>) -> Self {
+        const { assert!(mem::align_of::<T>() >= 2, "`PtrCow` requires an alignment of 2 or more") };
+
+        PtrCow {
+            ptr: NonNull::from(Box::leak(value)).map_addr(|addr| addr | OWNED),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns whether `self` owns its pointee.
+    pub fn is_owned(&self) -> bool {
+        self.ptr.addr().get() & OWNED != 0
+    }
+
+    /// Returns whether `self` borrows its pointee.
+    pub fn is_borrowed(&self) -> bool {
+        !self.is_owned()
+    }
+
+    /// Converts `self` into an owned `Box<T>`, cloning the pointee if it is borrowed.
+    pub fn into_owned(self) -> Box<T>
+    where
+        T: Clone,
+    {
+        let this = mem::ManuallyDrop::new(self);
+        if this.is_owned() {
+            unsafe { Box::from_raw(this.untagged().as_ptr()) }
+        } else {
+            Box::new((**this).clone())
+        }
+    }
+
+    /// Returns the pointer with the tag bit cleared.
+    fn untagged(&self) -> NonNull<T> {
+        // The address of a valid pointer to `T` is non-zero even without the tag bit.
+        unsafe { NonNull::new_unchecked(self.ptr.as_ptr().map_addr(|addr| addr & !OWNED)) }
+    }
+}
+
+impl<'a, T> Deref for PtrCow<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.untagged().as_ref() }
+    }
+}
+
+impl<'a, T> Drop for PtrCow<'a, T> {
+    fn drop(&mut self) {
+        if self.is_owned() {
+            unsafe { drop(Box::from_raw(self.untagged().as_ptr())); }
+        }
+    }
+}
+
+impl<'a, T: Clone> Clone for PtrCow<'a, T> {
+    fn clone(&self) -> Self {
+        if self.is_owned() {
+            PtrCow::owned(Box::new((**self).clone()))
+        } else {
+            PtrCow {
+                ptr: self.ptr,
+                _marker: PhantomData,
+            }
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for PtrCow<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = if self.is_owned() { "Owned" } else { "Borrowed" };
+        f.debug_tuple(variant).field(&**self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn borrowed_and_owned() {
+        let value = [1u32, 2];
+        let borrowed = PtrCow::borrowed(&value);
+        assert!(borrowed.is_borrowed());
+        assert_eq!(*borrowed, [1, 2]);
+        assert!(borrowed.clone().is_borrowed());
+
+        let owned = borrowed.into_owned();
+        assert_eq!(*owned, [1, 2]);
+        let owned = PtrCow::owned(owned);
+        assert!(owned.clone().is_owned());
+        assert_eq!(*owned.into_owned(), [1, 2]);
+        assert_eq!(mem::size_of::<Option<PtrCow<u32>>>(), mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn drops_owned_pointee() {
+        let rc = Rc::new(());
+        let borrowed = PtrCow::borrowed(&rc);
+        let owned = PtrCow::owned(Box::new(rc.clone()));
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(owned);
+        drop(borrowed);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
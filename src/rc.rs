@@ -0,0 +1,121 @@
+//! Constructing `Rc` and `Arc` payloads in place.
+//!
+//! [`new_uninit_rc`] and [`new_uninit_arc`] allocate a reference-counted, empty
+//! `UntaggedOption<T>`. While the pointer is still unique, the payload can be initialized through
+//! `Rc::get_mut` or `Arc::get_mut` with any of the `UntaggedOption` methods, and the pointer is
+//! then converted into an `Rc<T>` or `Arc<T>` with [`assume_init_rc`] or [`assume_init_arc`].
+//!
+//! ```
+//! # extern crate untagged_option;
+//! # use untagged_option::rc::{assume_init_arc, new_uninit_arc};
+//! # use untagged_option::UntaggedOption;
+//! # use std::sync::Arc;
+//! # fn main() {
+//! let mut config = new_uninit_arc::<[u32; 64]>();
+//! *Arc::get_mut(&mut config).unwrap() = UntaggedOption::some([7; 64]);
+//!
+//! let config: Arc<[u32; 64]> = unsafe { assume_init_arc(config) };
+//! assert_eq!(config[63], 7);
+//! # }
+//! ```
+//!
+//! [`new_uninit_rc`]: crate::rc::new_uninit_rc
+//! [`new_uninit_arc`]: crate::rc::new_uninit_arc
+//! [`assume_init_rc`]: crate::rc::assume_init_rc
+//! [`assume_init_arc`]: crate::rc::assume_init_arc
+
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+
+use core::mem::MaybeUninit;
+
+use poison;
+use UntaggedOption;
+
+/// Allocates an `Rc` holding an empty `UntaggedOption<T>`.
+///
+/// The payload is not initialized, so no `T` is moved to the heap.
+pub fn new_uninit_rc<T>() -> Rc<UntaggedOption<T>> {
+    let uninit: Rc<MaybeUninit<T>> = Rc::new_uninit();
+    // Safe: both types have the same layout, and an empty `UntaggedOption` is uninitialized.
+    let mut rc = unsafe { Rc::from_raw(Rc::into_raw(uninit) as *const UntaggedOption<T>) };
+    if UntaggedOption::<T>::is_poisoning() {
+        poison::poison(Rc::get_mut(&mut rc).unwrap());
+    }
+    rc
+}
+
+/// Converts an `Rc<UntaggedOption<T>>` into an `Rc<T>` without moving the payload.
+///
+/// # Safety
+///
+/// The payload of `rc` must hold a valid `T`.
+pub unsafe fn assume_init_rc<T>(rc: Rc<UntaggedOption<T>>) -> Rc<T> {
+    // `UntaggedOption<T>` has the same size and alignment as `T`, as `Rc::from_raw` requires.
+    Rc::from_raw(Rc::into_raw(rc) as *const T)
+}
+
+/// Allocates an `Arc` holding an empty `UntaggedOption<T>`.
+///
+/// The payload is not initialized, so no `T` is moved to the heap.
+#[cfg(target_has_atomic = "ptr")]
+pub fn new_uninit_arc<T>() -> Arc<UntaggedOption<T>> {
+    let uninit: Arc<MaybeUninit<T>> = Arc::new_uninit();
+    // Safe: both types have the same layout, and an empty `UntaggedOption` is uninitialized.
+    let mut arc = unsafe { Arc::from_raw(Arc::into_raw(uninit) as *const UntaggedOption<T>) };
+    if UntaggedOption::<T>::is_poisoning() {
+        poison::poison(Arc::get_mut(&mut arc).unwrap());
+    }
+    arc
+}
+
+/// Converts an `Arc<UntaggedOption<T>>` into an `Arc<T>` without moving the payload.
+///
+/// # Safety
+///
+/// The payload of `arc` must hold a valid `T`.
+#[cfg(target_has_atomic = "ptr")]
+pub unsafe fn assume_init_arc<T>(arc: Arc<UntaggedOption<T>>) -> Arc<T> {
+    // `UntaggedOption<T>` has the same size and alignment as `T`, as `Arc::from_raw` requires.
+    Arc::from_raw(Arc::into_raw(arc) as *const T)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+
+    #[test]
+    fn rc_in_place() {
+        let mut rc = new_uninit_rc::<String>();
+        unsafe {
+            let mut is_some = false;
+            Rc::get_mut(&mut rc).unwrap().or_insert(&mut is_some, String::from("init"));
+        }
+        let rc = unsafe { assume_init_rc(rc) };
+        let other = rc.clone();
+        assert_eq!(*other, "init");
+        drop(rc);
+        assert_eq!(Rc::strong_count(&other), 1);
+    }
+
+    #[test]
+    fn arc_in_place() {
+        let mut arc = new_uninit_arc::<u64>();
+        *Arc::get_mut(&mut arc).unwrap() = UntaggedOption::some(42);
+        let arc = unsafe { assume_init_arc(arc) };
+        assert_eq!(*arc, 42);
+    }
+
+    #[test]
+    fn large_payload() {
+        // Far larger than the stack of a test thread, so this only works if nothing is moved.
+        let mut arc = new_uninit_arc::<[u8; 64 << 20]>();
+        let opt = Arc::get_mut(&mut arc).unwrap();
+        unsafe {
+            opt.as_mut_ptr().write_bytes(1, 1);
+            assert_eq!(assume_init_arc(arc)[(64 << 20) - 1], 1);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! A link-time registry of static slots.
+//!
+//! Statics registered with [`register_slot!`] get a [`SlotDescriptor`] placed into a dedicated
+//! linker section, and [`slots`] returns all descriptors in the final binary. This lets
+//! subsystems, such as drivers, announce their state slots without a central list that has to be
+//! kept up to date.
+//!
+//! The registry relies on the linker defining `__start_` and `__stop_` symbols for the section,
+//! which GNU ld and LLD do for ELF targets. Bare-metal linker scripts must keep the
+//! `untagged_option_slots` section (for example through `KEEP(*(untagged_option_slots))`).
+//!
+//! Slots declared as `static mut` can be registered with `register_slot!(mut NAME)`. Their
+//! descriptors also provide an [`ErasedSlot`] pointer, through which a shutdown routine can tear
+//! down all registered slots without knowing their types.
+//!
+//! ```
+//! # #[macro_use] extern crate untagged_option;
+//! # use untagged_option::FrozenOption;
+//! # use untagged_option::registry;
+//! static UART_STATE: FrozenOption<u32> = FrozenOption::new();
+//! register_slot!("uart", UART_STATE);
+//!
+//! # fn main() {
+//! let uart = registry::slots().iter().find(|slot| slot.name() == "uart").unwrap();
+//! assert!(uart.is(&UART_STATE));
+//! # }
+//! ```
+//!
+//! [`register_slot!`]: crate::register_slot
+//! [`SlotDescriptor`]: crate::registry::SlotDescriptor
+//! [`slots`]: crate::registry::slots
+//! [`ErasedSlot`]: crate::ErasedSlot
+
+use core::mem;
+#[doc(hidden)]
+pub use core::ptr::addr_of_mut;
+use core::ptr;
+use core::slice;
+
+use erased::ErasedSlot;
+
+/// Describes a static slot registered with [`register_slot!`].
+///
+/// [`register_slot!`]: crate::register_slot
+#[derive(Debug)]
+#[repr(C)]
+pub struct SlotDescriptor {
+    name: &'static str,
+    addr: *const (),
+    size: usize,
+    erased: Option<*mut dyn ErasedSlot>,
+}
+
+unsafe impl Sync for SlotDescriptor {}
+
+impl SlotDescriptor {
+    /// Creates a descriptor of `slot`.
+    ///
+    /// This is used by [`register_slot!`] and normally doesn't need to be called directly.
+    ///
+    /// [`register_slot!`]: crate::register_slot
+    pub const fn new<T: Sync>(name: &'static str, slot: &'static T) -> Self {
+        SlotDescriptor {
+            name,
+            addr: slot as *const T as *const (),
+            size: mem::size_of::<T>(),
+            erased: None,
+        }
+    }
+
+    /// Creates a descriptor of the mutable slot at `slot`, providing type-erased access to it.
+    ///
+    /// This is used by [`register_slot!`] and normally doesn't need to be called directly.
+    ///
+    /// [`register_slot!`]: crate::register_slot
+    pub const fn new_erased<T: ErasedSlot + 'static>(name: &'static str, slot: *mut T) -> Self {
+        SlotDescriptor {
+            name,
+            addr: slot as *const (),
+            size: mem::size_of::<T>(),
+            erased: Some(slot as *mut dyn ErasedSlot),
+        }
+    }
+
+    /// Returns the name the slot was registered under.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the address of the slot.
+    pub fn addr(&self) -> *const () {
+        self.addr
+    }
+
+    /// Returns the size of the slot in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns a type-erased pointer to the slot, if it was registered as `static mut`.
+    ///
+    /// Accessing the slot through the pointer is subject to the usual rules for `static mut`
+    /// items: nothing else may access the slot at the same time.
+    pub fn erased(&self) -> Option<*mut dyn ErasedSlot> {
+        self.erased
+    }
+
+    /// Returns whether this descriptor describes `slot`.
+    pub fn is<T>(&self, slot: &'static T) -> bool {
+        self.addr == slot as *const T as *const ()
+    }
+}
+
+/// Registers a `static` slot in the link-time registry.
+///
+/// `register_slot!(NAME)` registers the static `NAME` under its own name, and
+/// `register_slot!("name", NAME)` registers it under a custom name. The static must be `Sync`.
+///
+/// A `static mut` that implements [`ErasedSlot`] is registered with `register_slot!(mut NAME)`
+/// or `register_slot!("name", mut NAME)` instead, which makes it available through
+/// [`SlotDescriptor::erased`].
+///
+/// The macro can also be invoked by its path, without `#[macro_use]`:
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::FrozenOption;
+/// static GPS_FIX: FrozenOption<[i32; 2]> = FrozenOption::new();
+/// untagged_option::register_slot!(GPS_FIX);
+///
+/// # fn main() {
+/// assert!(untagged_option::registry::slots().iter().any(|slot| slot.name() == "GPS_FIX"));
+/// # }
+/// ```
+///
+/// See the [`registry`] module for details.
+///
+/// [`registry`]: crate::registry
+/// [`ErasedSlot`]: crate::ErasedSlot
+/// [`SlotDescriptor::erased`]: crate::registry::SlotDescriptor::erased
+#[macro_export]
+macro_rules! register_slot {
+    (mut $slot:ident) => {
+        $crate::register_slot!(stringify!($slot), mut $slot);
+    };
+    ($slot:ident) => {
+        $crate::register_slot!(stringify!($slot), $slot);
+    };
+    ($name:expr, mut $slot:path) => {
+        const _: () = {
+            #[used]
+            #[link_section = "untagged_option_slots"]
+            static DESCRIPTOR: $crate::registry::SlotDescriptor =
+                $crate::registry::SlotDescriptor::new_erased(
+                    $name,
+                    $crate::registry::addr_of_mut!($slot),
+                );
+        };
+    };
+    ($name:expr, $slot:path) => {
+        const _: () = {
+            #[used]
+            #[link_section = "untagged_option_slots"]
+            static DESCRIPTOR: $crate::registry::SlotDescriptor =
+                $crate::registry::SlotDescriptor::new($name, &$slot);
+        };
+    };
+}
+
+/// Makes sure the section exists, so that its bounds are defined even if no slot is registered.
+#[used]
+#[link_section = "untagged_option_slots"]
+static EMPTY: [SlotDescriptor; 0] = [];
+
+// Only the addresses of these symbols are used.
+extern "C" {
+    #[link_name = "__start_untagged_option_slots"]
+    static START: u8;
+    #[link_name = "__stop_untagged_option_slots"]
+    static STOP: u8;
+}
+
+/// Returns the descriptors of all slots registered in the binary, in unspecified order.
+pub fn slots() -> &'static [SlotDescriptor] {
+    unsafe {
+        let start = ptr::addr_of!(START) as *const SlotDescriptor;
+        let stop = ptr::addr_of!(STOP) as *const SlotDescriptor;
+        let len = (stop as usize - start as usize) / mem::size_of::<SlotDescriptor>();
+        slice::from_raw_parts(start, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flagged::Flagged;
+    use frozen::FrozenOption;
+    use policy::Checked;
+    use UntaggedOption;
+
+    static FIRST: FrozenOption<u8> = FrozenOption::new();
+    static SECOND: FrozenOption<[u8; 3]> = FrozenOption::new();
+
+    register_slot!(FIRST);
+    register_slot!("second", SECOND);
+
+    static mut FLAGGED: Flagged<u64> = Flagged::some(1);
+    static mut CHECKED: UntaggedOption<u32, Checked> = UntaggedOption::some_with_policy(2);
+
+    register_slot!(mut FLAGGED);
+    register_slot!("checked", mut CHECKED);
+
+    #[test]
+    fn registered_slots_are_found() {
+        let first = slots().iter().find(|slot| slot.name() == "FIRST").unwrap();
+        assert!(first.is(&FIRST));
+        assert!(!first.is(&SECOND));
+        assert_eq!(first.size(), mem::size_of::<FrozenOption<u8>>());
+
+        let second = slots().iter().find(|slot| slot.name() == "second").unwrap();
+        assert_eq!(second.addr(), &SECOND as *const _ as *const ());
+        assert!(second.erased().is_none());
+    }
+
+    #[test]
+    fn tear_down_erased_slots() {
+        let erased = slots().iter().filter_map(|slot| slot.erased());
+        let mut torn_down = 0;
+        for slot in erased {
+            unsafe {
+                if (*slot).is_probably_init() == Some(true) {
+                    (*slot).drop_in_place();
+                    torn_down += 1;
+                }
+                assert_eq!((*slot).is_probably_init(), Some(false));
+            }
+        }
+        assert_eq!(torn_down, 2);
+    }
+}
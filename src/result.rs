@@ -0,0 +1,161 @@
+//! An untagged alternative to `Result<T, E>`.
+
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+/// A `Result<T, E>` without discriminant.
+///
+/// Like [`UntaggedOption`], this type does not know which variant it holds, so all accessors are
+/// unsafe and the caller has to track the variant elsewhere, usually in a flag shared with other
+/// values. The payload is never dropped automatically: call [`take_ok`] or [`take_err`] to get
+/// it back out.
+///
+/// `UntaggedResult<T, E>` is `#[repr(C)]`, its size is the larger of the sizes of `T` and `E`,
+/// and its alignment the larger of their alignments. Both payloads start at offset 0.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::UntaggedResult;
+/// # fn main() {
+/// let mut reading = UntaggedResult::<u16, u8>::err(3);
+/// let is_ok = false;
+///
+/// unsafe {
+///     if is_ok {
+///         println!("value: {}", reading.as_ok_ref());
+///     } else {
+///         assert_eq!(reading.take_err(), 3);
+///     }
+/// }
+/// # }
+/// ```
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+/// [`take_ok`]: #method.take_ok
+/// [`take_err`]: #method.take_err
+#[repr(C)]
+pub union UntaggedResult<T, E> {
+    ok: ManuallyDrop<T>,
+    err: ManuallyDrop<E>,
+}
+
+impl<T, E> UntaggedResult<T, E> {
+    /// Creates an `UntaggedResult` holding the success value `t`.
+    pub const fn ok(t: T) -> Self {
+        UntaggedResult {
+            ok: ManuallyDrop::new(t),
+        }
+    }
+
+    /// Creates an `UntaggedResult` holding the error value `e`.
+    pub const fn err(e: E) -> Self {
+        UntaggedResult {
+            err: ManuallyDrop::new(e),
+        }
+    }
+
+    /// Takes the success value out of `self`, leaving it uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedResult::ok`] creates
+    /// such a result.
+    ///
+    /// [`UntaggedResult::ok`]: #method.ok
+    pub const unsafe fn take_ok(&mut self) -> T {
+        ptr::read(ptr::addr_of!(self.ok) as *const T)
+    }
+
+    /// Takes the error value out of `self`, leaving it uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `E`. [`UntaggedResult::err`]
+    /// creates such a result.
+    ///
+    /// [`UntaggedResult::err`]: #method.err
+    pub const unsafe fn take_err(&mut self) -> E {
+        ptr::read(ptr::addr_of!(self.err) as *const E)
+    }
+
+    /// Obtains an immutable reference to the success value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn as_ok_ref(&self) -> &T {
+        &self.ok
+    }
+
+    /// Obtains an immutable reference to the error value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `E`.
+    pub unsafe fn as_err_ref(&self) -> &E {
+        &self.err
+    }
+
+    /// Obtains a mutable reference to the success value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn as_ok_mut(&mut self) -> &mut T {
+        &mut self.ok
+    }
+
+    /// Obtains a mutable reference to the error value.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `E`.
+    pub unsafe fn as_err_mut(&mut self) -> &mut E {
+        &mut self.err
+    }
+
+    /// Converts `self` into a `Result`, using `is_ok` to pick the variant.
+    ///
+    /// # Safety
+    ///
+    /// If `is_ok` is `true`, `self` must hold a valid `T`; otherwise it must hold a valid `E`.
+    pub unsafe fn into_result(mut self, is_ok: bool) -> Result<T, E> {
+        if is_ok {
+            Ok(self.take_ok())
+        } else {
+            Err(self.take_err())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use std::rc::Rc;
+
+    #[test]
+    fn layout() {
+        assert_eq!(mem::size_of::<UntaggedResult<u32, u8>>(), 4);
+        assert_eq!(mem::size_of::<UntaggedResult<u8, [u8; 3]>>(), 3);
+        assert_eq!(mem::align_of::<UntaggedResult<u8, u64>>(), 8);
+    }
+
+    #[test]
+    fn variants() {
+        let rc = Rc::new(());
+        let mut res = UntaggedResult::<Rc<()>, u8>::ok(rc.clone());
+        unsafe {
+            assert_eq!(Rc::strong_count(res.as_ok_ref()), 2);
+            drop(res.take_ok());
+            assert_eq!(Rc::strong_count(&rc), 1);
+
+            res = UntaggedResult::err(7);
+            *res.as_err_mut() += 1;
+            assert_eq!(*res.as_err_ref(), 8);
+            assert_eq!(res.into_result(false), Err(8));
+        }
+    }
+}
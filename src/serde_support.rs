@@ -0,0 +1,149 @@
+//! Serde support for options whose presence is tracked elsewhere.
+//!
+//! Structs often keep an `UntaggedOption` field next to a flag or a length that says whether it
+//! holds a value. Such a field is serialized through a [`WithFlag`] adapter, which borrows the
+//! option together with its flag and is serialized like an `Option<T>`. Creating the adapter is
+//! the unsafe step, since the flag has to be accurate. An option whose flag lives right next to
+//! it can be a [`Flagged`] instead, which implements `Serialize` and `Deserialize` directly.
+//! Fields that always hold a value can be deserialized with [`always_some`].
+//!
+//! Available with the `serde` feature.
+//!
+//! ```
+//! # extern crate serde;
+//! # extern crate untagged_option;
+//! use serde::ser::{Serialize, SerializeStruct, Serializer};
+//! use untagged_option::{Flagged, UntaggedOption};
+//!
+//! struct Calibration {
+//!     valid: bool,
+//!     offset: UntaggedOption<i32>,
+//!     gain: Flagged<u16>,
+//! }
+//!
+//! impl Serialize for Calibration {
+//!     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+//!         let mut s = serializer.serialize_struct("Calibration", 2)?;
+//!         // Safe: `valid` tracks whether `offset` holds a value.
+//!         s.serialize_field("offset", unsafe { &self.offset.with_flag(self.valid) })?;
+//!         s.serialize_field("gain", &self.gain)?;
+//!         s.end()
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! [`WithFlag`]: crate::serde_support::WithFlag
+//! [`Flagged`]: crate::Flagged
+//! [`always_some`]: crate::always_some
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use flagged::Flagged;
+use UntaggedOption;
+
+/// An option borrowed together with its presence flag, which serializes like an `Option<T>`.
+///
+/// Created by [`UntaggedOption::with_flag`].
+///
+/// [`UntaggedOption::with_flag`]: crate::UntaggedOption::with_flag
+pub struct WithFlag<'a, T: 'a> {
+    opt: &'a UntaggedOption<T>,
+    is_some: bool,
+}
+
+impl<T> UntaggedOption<T> {
+    /// Borrows `self` together with its presence flag, for serializing it as an `Option<T>`.
+    ///
+    /// # Safety
+    ///
+    /// `is_some` must be `true` if and only if `self` holds a valid `T`.
+    pub unsafe fn with_flag(&self, is_some: bool) -> WithFlag<'_, T> {
+        WithFlag { opt: self, is_some }
+    }
+
+    /// Serializes the contained `T` with `serializer`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`. [`UntaggedOption::some`] creates
+    /// such an option.
+    ///
+    /// [`UntaggedOption::some`]: #method.some
+    pub unsafe fn serialize_some<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        self.as_ref().serialize(serializer)
+    }
+
+    /// Deserializes a `T` with `deserializer` and returns an option holding it.
+    pub fn deserialize_some<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(UntaggedOption::some)
+    }
+}
+
+impl<'a, T: Serialize> Serialize for WithFlag<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_some {
+            serializer.serialize_some(unsafe { self.opt.as_ref() })
+        } else {
+            serializer.serialize_none()
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Flagged<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Flagged<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::deserialize(deserializer).map(Flagged::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt;
+    use serde::de::value::{BorrowedStrDeserializer, Error, UnitDeserializer};
+    use std::string::ToString;
+
+    /// Formats a value through its `Serialize` impl.
+    struct Show<T>(T);
+
+    impl<T: Serialize> fmt::Display for Show<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.serialize(f)
+        }
+    }
+
+    struct Serialized<'a>(&'a UntaggedOption<u32>);
+
+    impl<'a> Serialize for Serialized<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            unsafe { self.0.serialize_some(serializer) }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let opt = UntaggedOption::some(1200u32);
+        let shown = Show(Serialized(&opt)).to_string();
+        assert_eq!(shown, "1200");
+
+        let de = BorrowedStrDeserializer::<Error>::new("ok");
+        let opt = UntaggedOption::<&str>::deserialize_some(de).unwrap();
+        assert_eq!(unsafe { opt.into_inner() }, "ok");
+        let flagged = Flagged::<u8>::deserialize(UnitDeserializer::<Error>::new()).unwrap();
+        assert!(flagged.is_none());
+    }
+}
@@ -0,0 +1,194 @@
+//! An option shared between interrupt handlers and thread context through critical sections.
+
+use core::cell::{RefCell, RefMut};
+use core::fmt;
+use core::mem;
+
+use critical_section::{CriticalSection, Mutex};
+
+use UntaggedOption;
+
+/// The option together with the flag that tracks whether it holds a value.
+struct Slot<T> {
+    value: UntaggedOption<T>,
+    full: bool,
+}
+
+impl<T> Slot<T> {
+    fn take(&mut self) -> Option<T> {
+        if mem::replace(&mut self.full, false) {
+            Some(unsafe { self.value.take() })
+        } else {
+            None
+        }
+    }
+
+    fn put(&mut self, value: T) -> Option<T> {
+        let old = self.take();
+        self.value.write(value);
+        self.full = true;
+        old
+    }
+}
+
+/// An [`UntaggedOption`] and its presence flag behind a `critical_section::Mutex`.
+///
+/// Every operation runs inside a critical section, so a `SharedOption` in a `static` can be used
+/// from interrupt handlers and thread context alike without any `unsafe`. This is the usual way to
+/// hand a peripheral or a buffer to an interrupt handler, or a result back from it.
+///
+/// A value that is still stored when the option is dropped is dropped with it.
+///
+/// Available with the `critical-section` feature.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::SharedOption;
+/// # fn main() {
+/// static SAMPLE: SharedOption<u16> = SharedOption::new();
+///
+/// // In the ADC interrupt handler:
+/// SAMPLE.put(512);
+///
+/// // In the main loop:
+/// assert_eq!(SAMPLE.take(), Some(512));
+/// assert_eq!(SAMPLE.take(), None);
+/// # }
+/// ```
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+pub struct SharedOption<T> {
+    slot: Mutex<RefCell<Slot<T>>>,
+}
+
+impl<T> SharedOption<T> {
+    /// Creates an empty option.
+    pub const fn new() -> Self {
+        SharedOption {
+            slot: Mutex::new(RefCell::new(Slot {
+                value: UntaggedOption::none(),
+                full: false,
+            })),
+        }
+    }
+
+    /// Returns whether the option holds a value.
+    pub fn is_some(&self) -> bool {
+        critical_section::with(|cs| self.borrow(cs).full)
+    }
+
+    /// Stores `value`, returning the value that was stored before, if any.
+    pub fn put(&self, value: T) -> Option<T> {
+        critical_section::with(|cs| self.borrow(cs).put(value))
+    }
+
+    /// Takes the value out of the option, leaving it empty.
+    pub fn take(&self) -> Option<T> {
+        critical_section::with(|cs| self.borrow(cs).take())
+    }
+
+    /// Calls `f` with the contents as an `Option`, inside a critical section.
+    ///
+    /// Whatever `f` leaves in the `Option` is stored back. Accessing `self` from within `f`
+    /// panics.
+    pub fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Option<T>) -> R,
+    {
+        critical_section::with(|cs| {
+            let mut slot = self.borrow(cs);
+            let mut opt = slot.take();
+            let result = f(&mut opt);
+            if let Some(value) = opt {
+                slot.put(value);
+            }
+            result
+        })
+    }
+
+    /// Returns the value, if any.
+    pub fn into_inner(self) -> Option<T> {
+        let mut this = mem::ManuallyDrop::new(self);
+        this.slot.get_mut().get_mut().take()
+    }
+
+    fn borrow<'cs>(&'cs self, cs: CriticalSection<'cs>) -> RefMut<'cs, Slot<T>> {
+        self.slot
+            .borrow(cs)
+            .try_borrow_mut()
+            .expect("`SharedOption` accessed from within `with`")
+    }
+}
+
+impl<T> Default for SharedOption<T> {
+    fn default() -> Self {
+        SharedOption::new()
+    }
+}
+
+impl<T> Drop for SharedOption<T> {
+    fn drop(&mut self) {
+        drop(self.slot.get_mut().get_mut().take());
+    }
+}
+
+impl<T> fmt::Debug for SharedOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedOption").field("is_some", &self.is_some()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn with_and_drop() {
+        let rc = Rc::new(());
+        let opt = SharedOption::new();
+        assert_eq!(opt.put(rc.clone()), None);
+        assert!(opt.put(rc.clone()).is_some());
+        let len = opt.with(|inner| {
+            assert!(inner.is_some());
+            Rc::strong_count(&rc)
+        });
+        assert_eq!(len, 2);
+        assert!(opt.is_some());
+        drop(opt);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let opt = SharedOption::<u8>::default();
+        opt.with(|inner| *inner = Some(4));
+        assert_eq!(opt.into_inner(), Some(4));
+    }
+
+    #[test]
+    fn handoff() {
+        const COUNT: u32 = 1000;
+        static OPT: SharedOption<u32> = SharedOption::new();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=COUNT {
+                    while !OPT.with(|opt| opt.is_none() && opt.replace(i).is_none()) {
+                        thread::yield_now();
+                    }
+                }
+            });
+            let mut sum = 0;
+            for _ in 0..COUNT {
+                loop {
+                    match OPT.take() {
+                        Some(value) => break sum += value,
+                        None => thread::yield_now(),
+                    }
+                }
+            }
+            assert_eq!(sum, COUNT * (COUNT + 1) / 2);
+        });
+    }
+}
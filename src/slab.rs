@@ -0,0 +1,193 @@
+//! Fixed-capacity object storage with automatically assigned indices.
+
+use core::fmt;
+use core::iter::Enumerate;
+
+use group::{self, OptionGroup};
+
+/// Storage for up to `N` values, each identified by the index of the slot it was put in.
+///
+/// [`insert`] stores a value in the first free slot and returns its index, which stays valid
+/// until the value is [`remove`]d. This is the usual object pool found in drivers and schedulers,
+/// without the per-slot discriminant of an array of `Option<T>`: occupancy is tracked in an
+/// [`OptionBitmap`] with one bit per slot, and finding a free slot scans it a word at a time.
+///
+/// Like [`OptionGroup`], which it is built on, the slab needs `W` bitmap words to track `N`
+/// slots; the default of one word allows up to 32. Values still stored when the slab is dropped
+/// are dropped with it.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::UntaggedSlab;
+/// # fn main() {
+/// let mut timers = UntaggedSlab::<u32, 4>::new();
+/// let a = timers.insert(100).unwrap();
+/// let b = timers.insert(250).unwrap();
+/// assert_eq!(timers.remove(a), Some(100));
+///
+/// // Freed slots are reused.
+/// assert_eq!(timers.insert(300), Ok(a));
+/// assert_eq!(timers.get(b), Some(&250));
+/// assert_eq!(timers.len(), 2);
+/// # }
+/// ```
+///
+/// [`insert`]: #method.insert
+/// [`remove`]: #method.remove
+/// [`OptionBitmap`]: crate::OptionBitmap
+/// [`OptionGroup`]: crate::OptionGroup
+pub struct UntaggedSlab<T, const N: usize, const W: usize = 1> {
+    slots: OptionGroup<T, N, W>,
+}
+
+impl<T, const N: usize, const W: usize> UntaggedSlab<T, N, W> {
+    /// Creates an empty slab.
+    pub const fn new() -> Self {
+        UntaggedSlab {
+            slots: OptionGroup::new(),
+        }
+    }
+
+    /// Stores `value` in the first free slot and returns the index of that slot.
+    ///
+    /// If the slab is full, `value` is returned as an error.
+    pub fn insert(&mut self, value: T) -> Result<usize, T> {
+        match self.slots.first_vacant() {
+            Some(index) => {
+                self.slots.insert(index, value);
+                Ok(index)
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Removes and returns the value at `index`, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        self.slots.remove(index)
+    }
+
+    /// Returns a reference to the value at `index`, if there is one.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.slots.get(index)
+    }
+
+    /// Returns a mutable reference to the value at `index`, if there is one.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.slots.get_mut(index)
+    }
+
+    /// Returns whether a value is stored at `index`.
+    pub fn contains(&self, index: usize) -> bool {
+        self.slots.is_present(index)
+    }
+
+    /// Returns the number of stored values.
+    pub fn len(&self) -> usize {
+        self.slots.count()
+    }
+
+    /// Returns whether the slab is empty.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns whether every slot is occupied.
+    pub fn is_full(&self) -> bool {
+        self.slots.first_vacant().is_none()
+    }
+
+    /// Returns the number of slots.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Removes and drops all values.
+    pub fn clear(&mut self) {
+        self.slots.take_all(|_, value| drop(value));
+    }
+
+    /// Returns an iterator over the stored values and their indices, in ascending index order.
+    pub fn iter(&self) -> Iter<'_, T, W> {
+        Iter {
+            slots: self.slots.iter().enumerate(),
+        }
+    }
+
+    /// Writes the occupancy of the slab to `out`, for diagnostics.
+    ///
+    /// This prints the number of stored values, the capacity and one character per slot: `x` for
+    /// an occupied slot and `.` for a free one.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "UntaggedSlab len={} capacity={} slots=", self.len(), N)?;
+        ::bitmap::write_flags(out, (0..N).map(|i| self.contains(i)))
+    }
+}
+
+impl<T, const N: usize, const W: usize> Default for UntaggedSlab<T, N, W> {
+    fn default() -> Self {
+        UntaggedSlab::new()
+    }
+}
+
+/// Iterator over the values in an [`UntaggedSlab`] and their indices.
+///
+/// [`UntaggedSlab`]: crate::UntaggedSlab
+pub struct Iter<'a, T: 'a, const W: usize> {
+    slots: Enumerate<group::Iter<'a, T, W>>,
+}
+
+impl<'a, T, const W: usize> Iterator for Iter<'a, T, W> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        loop {
+            if let (index, Some(value)) = self.slots.next()? {
+                return Some((index, value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[test]
+    fn fill_and_drop() {
+        let rc = Rc::new(());
+        let mut slab = UntaggedSlab::<Rc<()>, 40, 2>::new();
+        for i in 0..40 {
+            assert_eq!(slab.insert(rc.clone()), Ok(i));
+        }
+        assert!(slab.is_full());
+        assert!(slab.insert(rc.clone()).is_err());
+
+        drop(slab.remove(35));
+        drop(slab.remove(3));
+        let indices: Vec<_> = slab.iter().map(|(i, _)| i).take(4).collect();
+        assert_eq!(indices, [0, 1, 2, 4]);
+        assert_eq!(slab.insert(rc.clone()), Ok(3));
+        assert_eq!(Rc::strong_count(&rc), 40);
+
+        drop(slab);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn dump() {
+        let mut slab = UntaggedSlab::<u8, 4>::new();
+        slab.insert(1).unwrap();
+        slab.insert(2).unwrap();
+        slab.remove(0);
+        let mut out = String::new();
+        slab.dump(&mut out).unwrap();
+        assert_eq!(out, "UntaggedSlab len=1 capacity=4 slots=.x..");
+        slab.clear();
+        assert!(slab.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! A cell for statics that hands out a single mutable reference.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use error::AlreadyInitialized;
+use sync;
+use UntaggedOption;
+
+/// A `static` slot that is initialized once and then owned through a `&'static mut` reference.
+///
+/// This replaces the `static mut` pattern for peripherals and buffers that are set up in `main`
+/// and then handed to the code using them: [`init`] stores the value and returns the only
+/// mutable reference to it, so all further accesses are safe. Since that reference is unique,
+/// the cell itself never hands out the value again.
+///
+/// For values that are initialized once and then read from several places (for example from
+/// `main` and an interrupt handler), use [`FrozenOption`] instead, which hands out shared
+/// references.
+///
+/// The value is never dropped.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::StaticCell;
+/// # fn main() {
+/// static RX_BUFFER: StaticCell<[u8; 64]> = StaticCell::new();
+///
+/// let buffer: &'static mut [u8; 64] = RX_BUFFER.init([0; 64]);
+/// buffer[0] = 0x55;
+///
+/// assert!(RX_BUFFER.is_initialized());
+/// assert!(RX_BUFFER.try_init([0; 64]).is_err());
+/// # }
+/// ```
+///
+/// [`init`]: #method.init
+/// [`FrozenOption`]: crate::FrozenOption
+pub struct StaticCell<T> {
+    taken: AtomicU8,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+unsafe impl<T: Send> Sync for StaticCell<T> {}
+
+// Handing out a unique reference through a shared one is the point of this type; the flag makes
+// sure it happens at most once.
+#[allow(clippy::mut_from_ref)]
+impl<T> StaticCell<T> {
+    /// Creates an uninitialized cell.
+    pub const fn new() -> Self {
+        StaticCell {
+            taken: AtomicU8::new(0),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Returns whether the cell has been initialized.
+    pub fn is_initialized(&self) -> bool {
+        self.taken.load(Ordering::Relaxed) != 0
+    }
+
+    /// Stores `value` in the cell and returns a mutable reference to it.
+    ///
+    /// If the cell has already been initialized, `value` is returned in an [`AlreadyInitialized`]
+    /// error.
+    ///
+    /// [`AlreadyInitialized`]: crate::AlreadyInitialized
+    pub fn try_init(&'static self, value: T) -> Result<&'static mut T, AlreadyInitialized<T>> {
+        match self.try_uninit() {
+            Some(slot) => {
+                *slot = UntaggedOption::some(value);
+                Ok(unsafe { slot.as_mut() })
+            }
+            None => Err(AlreadyInitialized { value }),
+        }
+    }
+
+    /// Stores `value` in the cell and returns a mutable reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell has already been initialized.
+    pub fn init(&'static self, value: T) -> &'static mut T {
+        match self.try_init(value) {
+            Ok(value) => value,
+            Err(_) => panic!("`StaticCell` initialized twice"),
+        }
+    }
+
+    /// Marks the cell as initialized and returns its empty slot, so the value can be built in
+    /// place.
+    ///
+    /// Returns `None` if the cell has already been initialized.
+    pub fn try_uninit(&'static self) -> Option<&'static mut UntaggedOption<T>> {
+        let claimed =
+            sync::compare_exchange_u8(&self.taken, 0, 1, Ordering::Acquire, Ordering::Relaxed);
+        // Safe: only the caller that won the exchange gets a reference to the slot.
+        claimed.ok().map(|_| unsafe { &mut *self.slot.get() })
+    }
+}
+
+impl<T> Default for StaticCell<T> {
+    fn default() -> Self {
+        StaticCell::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn single_owner() {
+        static CELL: StaticCell<[u8; 4]> = StaticCell::new();
+
+        let winners = thread::scope(|s| {
+            let handles: Vec<_> = (0..8u8)
+                .map(|i| s.spawn(move || CELL.try_init([i; 4]).map(|owned| owned[0] = 0xFF)))
+                .collect();
+            handles.into_iter().filter_map(|h| h.join().unwrap().ok()).count()
+        });
+        assert_eq!(winners, 1);
+        assert!(CELL.try_uninit().is_none());
+    }
+}
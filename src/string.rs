@@ -0,0 +1,230 @@
+//! A fixed-capacity string.
+
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::slice;
+use core::str;
+
+use error::CapacityExceeded;
+use UntaggedOption;
+
+/// A string of up to `N` bytes stored inline.
+///
+/// Only the first [`len`] bytes of the untagged buffer are initialized, so creating a
+/// `FixedString` does not touch its storage. This makes it suitable for formatting log messages
+/// and display contents on devices without an allocator.
+///
+/// The string implements [`fmt::Write`], which fails once the capacity is exceeded. The part of
+/// the output that fit is kept.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::FixedString;
+/// # use std::fmt::Write;
+/// # fn main() {
+/// let mut line = FixedString::<16>::new();
+/// write!(line, "T={}C", 21).unwrap();
+/// assert_eq!(&*line, "T=21C");
+///
+/// assert!(line.try_push_str(" and then some").is_err());
+/// assert_eq!(line.len(), 5);
+/// # }
+/// ```
+///
+/// [`len`]: #method.len
+pub struct FixedString<const N: usize> {
+    len: usize,
+    buf: UntaggedOption<[u8; N]>,
+}
+
+impl<const N: usize> FixedString<N> {
+    /// Creates an empty string.
+    pub const fn new() -> Self {
+        FixedString {
+            len: 0,
+            buf: UntaggedOption::none(),
+        }
+    }
+
+    /// Returns the length of the string in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the maximum length of the string in bytes.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the contents of the string.
+    pub fn as_str(&self) -> &str {
+        // Safe: the first `len` bytes are initialized and hold valid UTF-8.
+        unsafe {
+            let bytes = slice::from_raw_parts(self.buf.as_ptr() as *const u8, self.len);
+            str::from_utf8_unchecked(bytes)
+        }
+    }
+
+    /// Returns the contents of the string mutably.
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe {
+            let bytes = slice::from_raw_parts_mut(self.buf.as_mut_ptr() as *mut u8, self.len);
+            str::from_utf8_unchecked_mut(bytes)
+        }
+    }
+
+    /// Appends `s` to the string.
+    ///
+    /// If `s` does not fit, the string is left unchanged and an error is returned.
+    pub fn try_push_str(&mut self, s: &str) -> Result<(), CapacityExceeded> {
+        if s.len() > N - self.len {
+            return Err(CapacityExceeded { capacity: N });
+        }
+        unsafe {
+            let dest = (self.buf.as_mut_ptr() as *mut u8).add(self.len);
+            ptr::copy_nonoverlapping(s.as_ptr(), dest, s.len());
+        }
+        self.len += s.len();
+        Ok(())
+    }
+
+    /// Appends `s` to the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` does not fit.
+    pub fn push_str(&mut self, s: &str) {
+        if self.try_push_str(s).is_err() {
+            panic!("`FixedString` capacity exceeded");
+        }
+    }
+
+    /// Appends `c` to the string.
+    ///
+    /// If `c` does not fit, the string is left unchanged and an error is returned.
+    pub fn try_push(&mut self, c: char) -> Result<(), CapacityExceeded> {
+        self.try_push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends `c` to the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `c` does not fit.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Shortens the string to `new_len` bytes. Does nothing if the string is not longer than that.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_len` does not lie on a `char` boundary.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len {
+            assert!(self.is_char_boundary(new_len), "new length is not a char boundary");
+            self.len = new_len;
+        }
+    }
+
+    /// Removes the contents of the string.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for FixedString<N> {
+    fn default() -> Self {
+        FixedString::new()
+    }
+}
+
+impl<const N: usize> Clone for FixedString<N> {
+    fn clone(&self) -> Self {
+        let mut copy = FixedString::new();
+        copy.push_str(self);
+        copy
+    }
+}
+
+impl<const N: usize> Deref for FixedString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> DerefMut for FixedString<N> {
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for FixedString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> PartialEq for FixedString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for FixedString<N> {}
+
+impl<'a, const N: usize> PartialEq<&'a str> for FixedString<N> {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn push_and_format() {
+        let mut s = FixedString::<8>::new();
+        assert!(s.is_empty());
+        s.push_str("ab");
+        s.push('é');
+        assert_eq!(s, "abé");
+        assert_eq!(s.len(), 4);
+
+        assert_eq!(write!(s, "{}", 12345), Err(fmt::Error));
+        write!(s, "{}", 123).unwrap();
+        assert_eq!(s.try_push('é'), Err(CapacityExceeded { capacity: 8 }));
+        assert_eq!(s.try_push('!'), Ok(()));
+        assert_eq!(s, "abé123!");
+
+        s.truncate(2);
+        s.make_ascii_uppercase();
+        assert_eq!(s.clone(), "AB");
+        s.clear();
+        assert_eq!(s, "");
+    }
+}
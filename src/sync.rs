@@ -0,0 +1,94 @@
+//! Compare-and-swap that works on targets without atomic read-modify-write instructions.
+//!
+//! With the `critical-section` feature, the exchange is performed inside a critical section
+//! using plain atomic loads and stores, which every target supports. Otherwise, the native
+//! atomic instruction is used, and each helper only exists on targets that have it for its width.
+
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Stores `new` into `atomic` if it currently holds `current`.
+///
+/// Returns the previous value, wrapped in `Ok` if the exchange succeeded and in `Err` if not.
+#[cfg(all(target_has_atomic = "8", not(feature = "critical-section")))]
+pub(crate) fn compare_exchange_u8(
+    atomic: &AtomicU8,
+    current: u8,
+    new: u8,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<u8, u8> {
+    atomic.compare_exchange(current, new, success, failure)
+}
+
+/// Stores `new` into `atomic` if it currently holds `current`.
+///
+/// Returns the previous value, wrapped in `Ok` if the exchange succeeded and in `Err` if not.
+#[cfg(all(target_has_atomic = "ptr", not(feature = "critical-section")))]
+pub(crate) fn compare_exchange_usize(
+    atomic: &AtomicUsize,
+    current: usize,
+    new: usize,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<usize, usize> {
+    atomic.compare_exchange(current, new, success, failure)
+}
+
+/// Stores `new` into `atomic` if it currently holds `current`.
+///
+/// Returns the previous value, wrapped in `Ok` if the exchange succeeded and in `Err` if not.
+#[cfg(feature = "critical-section")]
+pub(crate) fn compare_exchange_u8(
+    atomic: &AtomicU8,
+    current: u8,
+    new: u8,
+    _success: Ordering,
+    _failure: Ordering,
+) -> Result<u8, u8> {
+    critical_section::with(|_| {
+        let value = atomic.load(Ordering::Acquire);
+        if value == current {
+            atomic.store(new, Ordering::Release);
+            Ok(value)
+        } else {
+            Err(value)
+        }
+    })
+}
+
+/// Stores `new` into `atomic` if it currently holds `current`.
+///
+/// Returns the previous value, wrapped in `Ok` if the exchange succeeded and in `Err` if not.
+#[cfg(feature = "critical-section")]
+pub(crate) fn compare_exchange_usize(
+    atomic: &AtomicUsize,
+    current: usize,
+    new: usize,
+    _success: Ordering,
+    _failure: Ordering,
+) -> Result<usize, usize> {
+    critical_section::with(|_| {
+        let value = atomic.load(Ordering::Acquire);
+        if value == current {
+            atomic.store(new, Ordering::Release);
+            Ok(value)
+        } else {
+            Err(value)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchange() {
+        let a = AtomicU8::new(1);
+        assert_eq!(compare_exchange_u8(&a, 1, 2, Ordering::AcqRel, Ordering::Acquire), Ok(1));
+        assert_eq!(compare_exchange_u8(&a, 1, 3, Ordering::AcqRel, Ordering::Acquire), Err(2));
+        let b = AtomicUsize::new(5);
+        assert_eq!(compare_exchange_usize(&b, 4, 6, Ordering::AcqRel, Ordering::Acquire), Err(5));
+        assert_eq!(b.load(Ordering::Relaxed), 5);
+    }
+}
@@ -0,0 +1,200 @@
+//! A slot whose updates are staged and then committed or aborted.
+
+use core::mem;
+
+use UntaggedOption;
+
+/// `current` holds a value.
+const CURRENT: u8 = 1 << 0;
+/// `staged` holds a value.
+const STAGED: u8 = 1 << 1;
+
+/// A slot holding a current value and optionally a staged replacement.
+///
+/// A new value is first [staged] next to the current one, where it can be inspected and
+/// modified. It then either replaces the current value on [`commit`], or is discarded on
+/// [`abort`], so the current value is never observed half-updated.
+///
+/// Both values are stored untagged; their presence is tracked in a single state byte.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::TransactionSlot;
+/// # fn main() {
+/// let mut config = TransactionSlot::new();
+/// config.stage([10u8, 20]);
+/// config.commit();
+///
+/// config.stage([10, 0]);
+/// if config.staged().unwrap()[1] == 0 {
+///     // Invalid update, keep the old configuration.
+///     config.abort();
+/// }
+/// assert_eq!(config.current(), Some(&[10, 20]));
+/// # }
+/// ```
+///
+/// [staged]: #method.stage
+/// [`commit`]: #method.commit
+/// [`abort`]: #method.abort
+pub struct TransactionSlot<T> {
+    state: u8,
+    current: UntaggedOption<T>,
+    staged: UntaggedOption<T>,
+}
+
+impl<T> TransactionSlot<T> {
+    /// Creates a slot with neither a current nor a staged value.
+    pub const fn new() -> Self {
+        TransactionSlot {
+            state: 0,
+            current: UntaggedOption::none(),
+            staged: UntaggedOption::none(),
+        }
+    }
+
+    /// Creates a slot with `value` as the current value.
+    pub const fn with_current(value: T) -> Self {
+        TransactionSlot {
+            state: CURRENT,
+            current: UntaggedOption::some(value),
+            staged: UntaggedOption::none(),
+        }
+    }
+
+    /// Returns a reference to the current value, if any.
+    pub fn current(&self) -> Option<&T> {
+        if self.state & CURRENT != 0 {
+            unsafe { Some(self.current.as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the staged value, if any.
+    pub fn staged(&self) -> Option<&T> {
+        if self.state & STAGED != 0 {
+            unsafe { Some(self.staged.as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the staged value, if any.
+    pub fn staged_mut(&mut self) -> Option<&mut T> {
+        if self.state & STAGED != 0 {
+            unsafe { Some(self.staged.as_mut()) }
+        } else {
+            None
+        }
+    }
+
+    /// Stages `value` to replace the current value, returning the previously staged value.
+    pub fn stage(&mut self, value: T) -> Option<T> {
+        if self.state & STAGED != 0 {
+            unsafe { Some(mem::replace(self.staged.as_mut(), value)) }
+        } else {
+            self.staged = UntaggedOption::some(value);
+            self.state |= STAGED;
+            None
+        }
+    }
+
+    /// Makes the staged value the current one, dropping the old current value.
+    ///
+    /// Returns `false` and leaves the current value in place if nothing was staged.
+    pub fn commit(&mut self) -> bool {
+        if self.state & STAGED == 0 {
+            return false;
+        }
+
+        self.take_current();
+        self.current = mem::replace(&mut self.staged, UntaggedOption::none());
+        self.state = CURRENT;
+        true
+    }
+
+    /// Drops the staged value, keeping the current value.
+    ///
+    /// Returns `false` if nothing was staged.
+    pub fn abort(&mut self) -> bool {
+        if self.state & STAGED == 0 {
+            return false;
+        }
+
+        self.state &= !STAGED;
+        unsafe { self.staged.take(); }
+        true
+    }
+
+    /// Removes and returns the current value, if any. A staged value is left in place.
+    pub fn take_current(&mut self) -> Option<T> {
+        if self.state & CURRENT != 0 {
+            self.state &= !CURRENT;
+            unsafe { Some(self.current.take()) }
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for TransactionSlot<T> {
+    fn default() -> Self {
+        TransactionSlot::new()
+    }
+}
+
+impl<T> Drop for TransactionSlot<T> {
+    fn drop(&mut self) {
+        self.abort();
+        self.take_current();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn stage_commit_abort() {
+        let mut slot = TransactionSlot::with_current(1);
+        assert!(!slot.commit());
+        assert!(!slot.abort());
+
+        assert_eq!(slot.stage(2), None);
+        assert_eq!(slot.stage(3), Some(2));
+        *slot.staged_mut().unwrap() += 1;
+        assert_eq!(slot.current(), Some(&1));
+        assert!(slot.commit());
+        assert_eq!(slot.current(), Some(&4));
+        assert_eq!(slot.staged(), None);
+
+        slot.stage(5);
+        assert!(slot.abort());
+        assert_eq!(slot.current(), Some(&4));
+        assert_eq!(slot.take_current(), Some(4));
+        assert_eq!(slot.current(), None);
+    }
+
+    #[test]
+    fn drops_replaced_values() {
+        let rc = Rc::new(());
+        {
+            let mut slot = TransactionSlot::new();
+            slot.stage(rc.clone());
+            slot.commit();
+            slot.stage(rc.clone());
+            assert_eq!(Rc::strong_count(&rc), 3);
+            slot.commit();
+            assert_eq!(Rc::strong_count(&rc), 2);
+            slot.stage(rc.clone());
+            slot.abort();
+            assert_eq!(Rc::strong_count(&rc), 2);
+            slot.stage(rc.clone());
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
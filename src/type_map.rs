@@ -0,0 +1,155 @@
+//! A fixed-capacity map from types to values.
+
+use core::any::TypeId;
+use core::fmt;
+
+use any_slot::AnySlot;
+
+/// A map holding at most one value per type, with room for `N` values of up to `S` bytes each.
+///
+/// This lets subsystems share singletons without a global allocator and without declaring a
+/// separate `static` for each of them: one `TypeMap` is set up at startup, and every subsystem
+/// looks up the values it needs by type. The values are stored inline in [`AnySlot`]s.
+///
+/// Lookups scan the slots linearly, which is fast for the small `N` this is meant for.
+///
+/// Like with [`AnySlot`], the values must be `Send` and `Sync`:
+///
+/// ```compile_fail
+/// # extern crate untagged_option;
+/// # use untagged_option::TypeMap;
+/// # use std::cell::Cell;
+/// # fn main() {
+/// let mut map = TypeMap::<2>::new();
+/// map.insert(Cell::new(0u8)).ok();
+/// # }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::TypeMap;
+/// # fn main() {
+/// struct Clock { hz: u32 }
+/// struct Baudrate(u32);
+///
+/// let mut services = TypeMap::<4>::new();
+/// services.insert(Clock { hz: 48_000_000 }).ok().unwrap();
+/// services.insert(Baudrate(115_200)).ok().unwrap();
+///
+/// assert_eq!(services.get::<Clock>().unwrap().hz, 48_000_000);
+/// assert_eq!(services.remove::<Baudrate>().unwrap().0, 115_200);
+/// assert!(!services.contains::<Baudrate>());
+/// # }
+/// ```
+///
+/// [`AnySlot`]: crate::AnySlot
+pub struct TypeMap<const N: usize, const S: usize = 32> {
+    slots: [AnySlot<S>; N],
+}
+
+impl<const N: usize, const S: usize> TypeMap<N, S> {
+    /// Creates an empty map.
+    pub const fn new() -> Self {
+        TypeMap {
+            slots: [const { AnySlot::new() }; N],
+        }
+    }
+
+    /// Returns the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| !slot.is_empty()).count()
+    }
+
+    /// Returns whether the map holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_empty())
+    }
+
+    /// Returns the maximum number of values the map can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Writes the occupancy of the map to `out`, for diagnostics.
+    ///
+    /// This prints the number of values, the capacity and one character per slot: `x` for an
+    /// occupied slot and `.` for an empty one. The values themselves are never printed.
+    pub fn dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        write!(out, "TypeMap len={} capacity={} slots=", self.len(), N)?;
+        ::bitmap::write_flags(out, self.slots.iter().map(|slot| !slot.is_empty()))
+    }
+
+    /// Returns whether the map holds a value of type `T`.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.position::<T>().is_some()
+    }
+
+    /// Stores `value` as the map's value of type `T`.
+    ///
+    /// Returns the value of type `T` that was previously stored, if any. If the map holds no value
+    /// of type `T` and all of its slots are occupied, `value` is handed back as an error.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Result<Option<T>, T> {
+        if let Some(i) = self.position::<T>() {
+            let old = self.slots[i].take::<T>();
+            self.slots[i].insert(value);
+            return Ok(old);
+        }
+
+        match self.slots.iter_mut().find(|slot| slot.is_empty()) {
+            Some(slot) => {
+                slot.insert(value);
+                Ok(None)
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Returns a reference to the value of type `T`, if there is one.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.slots.iter().find_map(|slot| slot.get::<T>())
+    }
+
+    /// Returns a mutable reference to the value of type `T`, if there is one.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.slots.iter_mut().find_map(|slot| slot.get_mut::<T>())
+    }
+
+    /// Removes and returns the value of type `T`, if there is one.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.slots.iter_mut().find_map(|slot| slot.take::<T>())
+    }
+
+    fn position<T: 'static>(&self) -> Option<usize> {
+        let type_id = Some(TypeId::of::<T>());
+        self.slots.iter().position(|slot| slot.type_id() == type_id)
+    }
+}
+
+impl<const N: usize, const S: usize> Default for TypeMap<N, S> {
+    fn default() -> Self {
+        TypeMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_value_per_type() {
+        let mut map = TypeMap::<2, 8>::new();
+        assert_eq!(map.insert(1u8), Ok(None));
+        assert_eq!(map.insert(2u8), Ok(Some(1)));
+        assert_eq!(map.insert(3u16), Ok(None));
+        assert_eq!(map.insert(4u32), Err(4));
+        assert_eq!(map.len(), 2);
+
+        *map.get_mut::<u16>().unwrap() += 1;
+        assert_eq!(map.get::<u16>(), Some(&4));
+        assert_eq!(map.remove::<u8>(), Some(2));
+        assert_eq!(map.insert(5u32), Ok(None));
+        assert_eq!(map.get::<u32>(), Some(&5));
+    }
+}
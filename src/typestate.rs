@@ -0,0 +1,205 @@
+//! Slots whose initialization state is tracked in their type.
+//!
+//! A [`Slot`] wraps an `UntaggedOption` and records in its second type parameter whether it
+//! holds a value. Filling a [`Slot<T, Uninit>`] turns it into a [`Slot<T, Init>`], and the
+//! accessors on the latter are safe, so code where the state is known statically does not need
+//! any `unsafe`.
+//!
+//! ```
+//! # extern crate untagged_option;
+//! # use untagged_option::typestate::{Init, Slot, Uninit};
+//! # fn main() {
+//! fn configure(slot: Slot<u32, Uninit>) -> Slot<u32, Init> {
+//!     slot.write(115_200)
+//! }
+//!
+//! let mut baud = configure(Slot::new());
+//! *baud.as_mut() /= 2;
+//! let (rate, _empty) = baud.take();
+//! assert_eq!(rate, 57_600);
+//! # }
+//! ```
+//!
+//! [`Slot`]: crate::typestate::Slot
+//! [`Slot<T, Uninit>`]: crate::typestate::Uninit
+//! [`Slot<T, Init>`]: crate::typestate::Init
+
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use UntaggedOption;
+
+/// Type state of a [`Slot`] that holds no value.
+///
+/// [`Slot`]: crate::typestate::Slot
+#[derive(Debug)]
+pub enum Uninit {}
+
+/// Type state of a [`Slot`] that holds a valid value.
+///
+/// [`Slot`]: crate::typestate::Slot
+#[derive(Debug)]
+pub enum Init {}
+
+/// An `UntaggedOption` whose initialization state `S` is [`Init`] or [`Uninit`].
+///
+/// A `Slot<T, Init>` dereferences to the contained value. A `Slot` has the same layout as an
+/// `UntaggedOption<T>`, and like it, a `Slot<T, Init>` does not drop its value when it goes out of
+/// scope; call [`take`] or [`into_inner`] to get it back.
+///
+/// [`Init`]: crate::typestate::Init
+/// [`Uninit`]: crate::typestate::Uninit
+/// [`take`]: #method.take
+/// [`into_inner`]: #method.into_inner
+#[repr(transparent)]
+pub struct Slot<T, S> {
+    opt: UntaggedOption<T>,
+    state: PhantomData<S>,
+}
+
+impl<T, S> Slot<T, S> {
+    /// Returns the underlying option, forgetting the state.
+    pub fn into_untagged(self) -> UntaggedOption<T> {
+        self.opt
+    }
+}
+
+impl<T> Slot<T, Uninit> {
+    /// Creates an empty slot.
+    pub const fn new() -> Self {
+        Slot {
+            opt: UntaggedOption::none(),
+            state: PhantomData,
+        }
+    }
+
+    /// Stores `value` in the slot.
+    pub fn write(mut self, value: T) -> Slot<T, Init> {
+        self.opt.write(value);
+        Slot {
+            opt: self.opt,
+            state: PhantomData,
+        }
+    }
+
+    /// Declares the slot initialized, for example after it was filled through a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// The underlying option must hold a valid `T`.
+    pub unsafe fn assume_init(mut self) -> Slot<T, Init> {
+        self.opt.mark_full();
+        Slot {
+            opt: self.opt,
+            state: PhantomData,
+        }
+    }
+
+    /// Returns a raw pointer to the payload, for initializing it in place.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.opt.as_mut_ptr()
+    }
+}
+
+impl<T> Default for Slot<T, Uninit> {
+    fn default() -> Self {
+        Slot::new()
+    }
+}
+
+impl<T> Slot<T, Init> {
+    /// Creates a slot holding `value`.
+    pub const fn some(value: T) -> Self {
+        Slot {
+            opt: UntaggedOption::some(value),
+            state: PhantomData,
+        }
+    }
+
+    /// Wraps an option that is known to hold a value.
+    ///
+    /// # Safety
+    ///
+    /// `opt` must hold a valid `T`.
+    pub unsafe fn from_untagged(opt: UntaggedOption<T>) -> Self {
+        Slot {
+            opt,
+            state: PhantomData,
+        }
+    }
+
+    /// Returns a copy of the contained value.
+    pub fn read(&self) -> T
+    where
+        T: Copy,
+    {
+        unsafe { self.opt.read() }
+    }
+
+    /// Takes the value out of the slot, returning it together with the now empty slot.
+    pub fn take(mut self) -> (T, Slot<T, Uninit>) {
+        let value = unsafe { self.opt.take() };
+        let empty = Slot {
+            opt: self.opt,
+            state: PhantomData,
+        };
+        (value, empty)
+    }
+
+    /// Moves the contained value out of the slot.
+    pub fn into_inner(self) -> T {
+        unsafe { self.opt.into_inner() }
+    }
+}
+
+impl<T> Deref for Slot<T, Init> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.opt.as_ref() }
+    }
+}
+
+impl<T> DerefMut for Slot<T, Init> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.opt.as_mut() }
+    }
+}
+
+impl<T> AsRef<T> for Slot<T, Init> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for Slot<T, Init> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use std::rc::Rc;
+
+    #[test]
+    fn transitions() {
+        assert_eq!(mem::size_of::<Slot<u64, Init>>(), mem::size_of::<UntaggedOption<u64>>());
+
+        let rc = Rc::new(());
+        let slot = Slot::<Rc<()>, Uninit>::default().write(rc.clone());
+        assert_eq!(Rc::strong_count(slot.as_ref()), 2);
+        let (value, mut slot) = slot.take();
+        drop(value);
+        assert_eq!(Rc::strong_count(&rc), 1);
+        unsafe { slot.as_mut_ptr().write(rc.clone()) };
+        let slot = unsafe { slot.assume_init() };
+        assert_eq!(Rc::strong_count(&slot.into_inner()), 2);
+
+        let slot = Slot::some(3u8);
+        assert_eq!(slot.read(), 3);
+        assert_eq!(unsafe { slot.into_untagged().into_inner() }, 3);
+    }
+}
@@ -0,0 +1,148 @@
+//! An untagged option for packed structs.
+
+use core::ptr;
+
+use UntaggedOption;
+
+/// An [`UntaggedOption`] with an alignment of 1, for use in `#[repr(packed)]` structs and
+/// wire-format layouts.
+///
+/// Creating a reference to a misaligned field is undefined behavior, so `UnalignedOption` never
+/// hands out references to its payload. All accessors copy the payload in or out with unaligned
+/// loads and stores instead.
+///
+/// Like `UntaggedOption`, this type does not know whether it holds a value, and never drops it.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::UnalignedOption;
+/// # use std::mem;
+/// # fn main() {
+/// #[repr(C, packed)]
+/// struct Frame {
+///     kind: u8,
+///     payload: UnalignedOption<u32>,
+/// }
+///
+/// assert_eq!(mem::size_of::<Frame>(), 5);
+///
+/// let mut frame = Frame { kind: 1, payload: UnalignedOption::none() };
+/// frame.payload.write(0xC0FFEE);
+/// assert_eq!(unsafe { frame.payload.read() }, 0xC0FFEE);
+/// # }
+/// ```
+///
+/// [`UntaggedOption`]: crate::UntaggedOption
+#[repr(C, packed)]
+pub struct UnalignedOption<T> {
+    slot: UntaggedOption<T>,
+}
+
+impl<T> UnalignedOption<T> {
+    /// Creates a new `UnalignedOption` holding no value.
+    pub const fn none() -> Self {
+        UnalignedOption {
+            slot: UntaggedOption::none(),
+        }
+    }
+
+    /// Creates an `UnalignedOption` containing `t`.
+    ///
+    /// `t` will not be dropped automatically; call [`take`] to get it back out.
+    ///
+    /// [`take`]: #method.take
+    pub const fn some(t: T) -> Self {
+        UnalignedOption {
+            slot: UntaggedOption::some(t),
+        }
+    }
+
+    /// Returns a raw pointer to the payload, which may be misaligned.
+    pub fn as_ptr(&self) -> *const T {
+        ptr::addr_of!(self.slot) as *const T
+    }
+
+    /// Returns a mutable raw pointer to the payload, which may be misaligned.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        ptr::addr_of_mut!(self.slot) as *mut T
+    }
+
+    /// Stores `value`, overwriting (and leaking) any value held before.
+    pub fn write(&mut self, value: T) {
+        unsafe { ptr::write_unaligned(self.as_mut_ptr(), value) }
+    }
+
+    /// Takes the `T` out of `self`, leaving it empty.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn take(&mut self) -> T {
+        let value = ptr::read_unaligned(self.as_ptr());
+        *self = UnalignedOption::none();
+        value
+    }
+
+    /// Stores `value` and returns the value held before.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn replace(&mut self, value: T) -> T {
+        let old = ptr::read_unaligned(self.as_ptr());
+        ptr::write_unaligned(self.as_mut_ptr(), value);
+        old
+    }
+}
+
+impl<T: Copy> UnalignedOption<T> {
+    /// Returns a copy of the contained `T`.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method requires that `self` holds a valid `T`.
+    pub unsafe fn read(&self) -> T {
+        ptr::read_unaligned(self.as_ptr())
+    }
+}
+
+impl<T> From<UntaggedOption<T>> for UnalignedOption<T> {
+    fn from(slot: UntaggedOption<T>) -> Self {
+        UnalignedOption { slot }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use std::rc::Rc;
+
+    #[repr(C, packed)]
+    struct Record {
+        tag: u8,
+        value: UnalignedOption<u64>,
+        owner: UnalignedOption<Rc<()>>,
+    }
+
+    #[test]
+    fn misaligned_fields() {
+        assert_eq!(mem::align_of::<UnalignedOption<u64>>(), 1);
+        let rc = Rc::new(());
+        let mut record = Record {
+            tag: 7,
+            value: UnalignedOption::some(1),
+            owner: UnalignedOption::none(),
+        };
+        record.owner.write(rc.clone());
+        unsafe {
+            assert_eq!(record.value.replace(2), 1);
+            assert_eq!(record.value.read(), 2);
+            drop(record.owner.take());
+        }
+        assert_eq!(Rc::strong_count(&rc), 1);
+        assert_eq!({ record.tag }, 7);
+    }
+}
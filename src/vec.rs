@@ -0,0 +1,113 @@
+//! Extensions for `Vec`.
+
+use alloc::vec::Vec;
+use core::slice;
+
+use poison;
+use UntaggedOption;
+
+/// Extension methods for filling a `Vec<T>`'s spare capacity through untagged slots.
+///
+/// This supports the common pattern of reserving capacity, initializing some of the elements
+/// past the end in place (for example by reading into them), and then extending the vector over
+/// them.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::VecExt;
+/// # use untagged_option::UntaggedOption;
+/// # fn main() {
+/// let mut samples = Vec::<u16>::with_capacity(8);
+/// samples.push(1);
+///
+/// let spare = samples.spare_slots();
+/// assert!(spare.len() >= 7);
+/// for (i, slot) in spare[..3].iter_mut().enumerate() {
+///     *slot = UntaggedOption::some(i as u16 * 10);
+/// }
+/// unsafe { samples.assume_init_push(3) };
+///
+/// assert_eq!(samples, [1, 0, 10, 20]);
+/// # }
+/// ```
+pub trait VecExt<T> {
+    /// Returns the spare capacity of the vector as untagged slots.
+    ///
+    /// All returned slots are empty. With the `poison` feature, they are filled with the poison
+    /// pattern, which takes time proportional to the spare capacity. Otherwise they hold whatever
+    /// bytes the allocation contains, such as those of elements that were removed.
+    ///
+    /// Storing values in the slots does not change the length of the vector; use
+    /// [`assume_init_push`] to include them.
+    ///
+    /// [`assume_init_push`]: #tymethod.assume_init_push
+    fn spare_slots(&mut self) -> &mut [UntaggedOption<T>];
+
+    /// Extends the vector over the first `n` slots of its spare capacity.
+    ///
+    /// # Safety
+    ///
+    /// The first `n` slots returned by [`spare_slots`] must hold valid values of type `T`. In
+    /// particular, `n` must not exceed the spare capacity.
+    ///
+    /// [`spare_slots`]: #tymethod.spare_slots
+    unsafe fn assume_init_push(&mut self, n: usize);
+}
+
+impl<T> VecExt<T> for Vec<T> {
+    fn spare_slots(&mut self) -> &mut [UntaggedOption<T>] {
+        let spare = self.spare_capacity_mut();
+        // Safe: `UntaggedOption<T>` has the same layout as `MaybeUninit<T>`, and no slot is
+        // treated as initialized.
+        let slots = unsafe {
+            slice::from_raw_parts_mut(spare.as_mut_ptr() as *mut UntaggedOption<T>, spare.len())
+        };
+        if UntaggedOption::<T>::is_poisoning() {
+            for slot in slots.iter_mut() {
+                poison::poison(slot);
+            }
+        }
+        slots
+    }
+
+    unsafe fn assume_init_push(&mut self, n: usize) {
+        debug_assert!(n <= self.capacity() - self.len());
+        let len = self.len();
+        self.set_len(len + n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn push_initialized_slots() {
+        let rc = Rc::new(());
+        let mut vec = Vec::with_capacity(4);
+        vec.push(rc.clone());
+        for slot in &mut vec.spare_slots()[..2] {
+            *slot = UntaggedOption::some(rc.clone());
+        }
+        unsafe { vec.assume_init_push(2) };
+        assert_eq!(vec.len(), 3);
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(vec);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "poison")]
+    fn spare_slots_are_poisoned() {
+        use poison::PoisonDetected;
+
+        let mut vec = Vec::from([7u32; 4]);
+        vec.truncate(1);
+        for slot in vec.spare_slots() {
+            assert_eq!(unsafe { slot.validate() }, Err(PoisonDetected));
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! A slot with a sequence counter for lock-free reads.
+
+use core::cell::UnsafeCell;
+use core::hint;
+use core::mem::{self, MaybeUninit};
+use core::sync::atomic::{fence, AtomicU8, AtomicUsize, Ordering};
+
+use no_padding::NoPadding;
+use sync;
+use UntaggedOption;
+
+/// A slot for `Copy` values whose writes are versioned by a sequence counter.
+///
+/// Every write increments the sequence counter twice: once before it starts (making the counter
+/// odd) and once after it finishes (making it even again). Readers copy the value out and retry
+/// if the counter changed in the meantime or was odd, so they never observe a torn value and
+/// never block writers. The number of completed writes is exposed as the slot's [`version`],
+/// which is useful for logging and for detecting updates.
+///
+/// Writers exclude each other through the counter as well: a write started while another one is
+/// in progress waits for it ([`write`]) or fails ([`try_write`]). Interrupt handlers that may
+/// preempt a writer must use `try_write`, since waiting would never end.
+///
+/// Since readers copy the value while it may be overwritten, both sides access the payload one
+/// byte at a time through relaxed atomic loads and stores, so the overlapping accesses are not a
+/// data race. The copy a reader makes during a write may be torn, and is discarded. Padding bytes
+/// are uninitialized and cannot be loaded into an atomic, so `T` must implement [`NoPadding`].
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::VersionedSlot;
+/// # fn main() {
+/// static POSITION: VersionedSlot<[i32; 2]> = VersionedSlot::new();
+///
+/// assert_eq!(POSITION.read(), None);
+/// POSITION.write([3, -4]);
+/// assert_eq!(POSITION.read_versioned(), Some(([3, -4], 1)));
+/// POSITION.write([5, 0]);
+/// assert_eq!(POSITION.version(), 2);
+/// # }
+/// ```
+///
+/// Payloads with padding bytes are rejected:
+///
+/// ```compile_fail
+/// # extern crate untagged_option;
+/// # use untagged_option::VersionedSlot;
+/// # fn main() {
+/// let slot = VersionedSlot::<(u8, u32)>::new();
+/// # }
+/// ```
+///
+/// [`NoPadding`]: crate::NoPadding
+/// [`version`]: #method.version
+/// [`write`]: #method.write
+/// [`try_write`]: #method.try_write
+pub struct VersionedSlot<T: Copy + NoPadding> {
+    seq: AtomicUsize,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+unsafe impl<T: Copy + NoPadding + Send> Sync for VersionedSlot<T> {}
+
+impl<T: Copy + NoPadding> VersionedSlot<T> {
+    /// Creates a slot that has never been written.
+    pub const fn new() -> Self {
+        VersionedSlot {
+            seq: AtomicUsize::new(0),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Returns the number of completed writes.
+    pub fn version(&self) -> usize {
+        self.seq.load(Ordering::Acquire) / 2
+    }
+
+    /// Stores `value`, failing if another write is in progress.
+    ///
+    /// Returns the version of the slot after the write, or `value` if the slot is being written
+    /// concurrently.
+    pub fn try_write(&self, value: T) -> Result<usize, T> {
+        let seq = self.seq.load(Ordering::Relaxed);
+        if seq & 1 != 0 {
+            return Err(value);
+        }
+        let claimed = sync::compare_exchange_usize(
+            &self.seq,
+            seq,
+            seq + 1,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        );
+        if claimed.is_err() {
+            return Err(value);
+        }
+        fence(Ordering::Release);
+
+        unsafe {
+            // Readers may copy the payload concurrently, but discard the copy when they notice
+            // that the counter was odd or changed.
+            store_atomic(self.slot.get() as *mut T, &value);
+        }
+
+        self.seq.store(seq + 2, Ordering::Release);
+        Ok(seq / 2 + 1)
+    }
+
+    /// Stores `value`, waiting for any write that is in progress to finish first.
+    ///
+    /// Returns the version of the slot after the write.
+    pub fn write(&self, mut value: T) -> usize {
+        loop {
+            match self.try_write(value) {
+                Ok(version) => return version,
+                Err(v) => value = v,
+            }
+            hint::spin_loop();
+        }
+    }
+
+    /// Returns a copy of the most recently written value, or `None` if the slot was never
+    /// written.
+    pub fn read(&self) -> Option<T> {
+        self.read_versioned().map(|(value, _)| value)
+    }
+
+    /// Returns a copy of the most recently written value together with its version, or `None` if
+    /// the slot was never written.
+    ///
+    /// The version counts the writes up to and including the one that stored the value.
+    pub fn read_versioned(&self) -> Option<(T, usize)> {
+        loop {
+            let before = self.seq.load(Ordering::Acquire);
+            if before == 0 {
+                return None;
+            }
+            if before & 1 == 0 {
+                let value = unsafe { load_atomic(self.slot.get() as *const T) };
+                fence(Ordering::Acquire);
+                if self.seq.load(Ordering::Relaxed) == before {
+                    // Safe: the counter did not change, so no write overlapped the copy.
+                    return Some((unsafe { value.assume_init() }, before / 2));
+                }
+            }
+            hint::spin_loop();
+        }
+    }
+}
+
+/// Copies `value` to `dest` with relaxed atomic stores, one byte at a time.
+unsafe fn store_atomic<T: Copy + NoPadding>(dest: *mut T, value: &T) {
+    let src = value as *const T as *const u8;
+    for i in 0..mem::size_of::<T>() {
+        AtomicU8::from_ptr((dest as *mut u8).add(i)).store(*src.add(i), Ordering::Relaxed);
+    }
+}
+
+/// Copies the value at `src` with relaxed atomic loads, one byte at a time.
+///
+/// The copy is only valid if no store overlapped it.
+unsafe fn load_atomic<T: Copy + NoPadding>(src: *const T) -> MaybeUninit<T> {
+    let mut value = MaybeUninit::<T>::uninit();
+    let dest = value.as_mut_ptr() as *mut u8;
+    for i in 0..mem::size_of::<T>() {
+        *dest.add(i) = AtomicU8::from_ptr((src as *mut u8).add(i)).load(Ordering::Relaxed);
+    }
+    value
+}
+
+impl<T: Copy + NoPadding> Default for VersionedSlot<T> {
+    fn default() -> Self {
+        VersionedSlot::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn versions() {
+        let slot = VersionedSlot::new();
+        assert_eq!(slot.version(), 0);
+        assert_eq!(slot.read_versioned(), None);
+        assert_eq!(slot.try_write(7u8), Ok(1));
+        assert_eq!(slot.write(8), 2);
+        assert_eq!(slot.read_versioned(), Some((8, 2)));
+    }
+
+    #[test]
+    fn no_torn_reads() {
+        let slot = VersionedSlot::new();
+        slot.write([0u64; 8]);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 1..=10_000u64 {
+                    slot.write([i; 8]);
+                }
+            });
+            s.spawn(|| {
+                for i in 1..=10_000u64 {
+                    slot.write([i << 32; 8]);
+                }
+            });
+            s.spawn(|| {
+                let mut last = 0;
+                while last < 20_001 {
+                    let (value, version) = slot.read_versioned().unwrap();
+                    assert!(value.iter().all(|&x| x == value[0]), "torn read");
+                    assert!(version >= last);
+                    last = version;
+                }
+            });
+        });
+    }
+}
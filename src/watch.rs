@@ -0,0 +1,204 @@
+//! A slot that notifies an observer when a value is stored.
+
+use core::cell::{Cell, UnsafeCell};
+
+use UntaggedOption;
+
+/// Decrements the reader count of a `WatchSlot` when dropped.
+struct ReadGuard<'a>(&'a Cell<usize>);
+
+impl<'a> ReadGuard<'a> {
+    fn new(readers: &'a Cell<usize>) -> Self {
+        readers.set(readers.get() + 1);
+        ReadGuard(readers)
+    }
+}
+
+impl<'a> Drop for ReadGuard<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// A single-threaded slot that calls a registered function whenever a new value is stored.
+///
+/// The callback is a plain function pointer, so it can be registered in a `const` context and
+/// doesn't need an allocation. It receives a reference to the newly stored value.
+///
+/// While the callback runs (or a closure passed to [`with`] inspects the value), the value is
+/// borrowed, so storing a new value is refused: [`set`] hands the value back instead of running
+/// the callback re-entrantly.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::WatchSlot;
+/// # use std::sync::atomic::{AtomicU32, Ordering};
+/// # fn main() {
+/// static LAST_SEEN: AtomicU32 = AtomicU32::new(0);
+///
+/// fn on_set(temperature: &u32) {
+///     LAST_SEEN.store(*temperature, Ordering::Relaxed);
+/// }
+///
+/// let slot = WatchSlot::with_callback(on_set);
+/// slot.set(21).unwrap();
+/// assert_eq!(LAST_SEEN.load(Ordering::Relaxed), 21);
+/// assert_eq!(slot.get(), Some(21));
+/// # }
+/// ```
+///
+/// [`with`]: #method.with
+/// [`set`]: #method.set
+pub struct WatchSlot<T> {
+    is_set: Cell<bool>,
+    readers: Cell<usize>,
+    callback: Cell<Option<fn(&T)>>,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+impl<T> WatchSlot<T> {
+    /// Creates an empty slot without a callback.
+    pub const fn new() -> Self {
+        WatchSlot {
+            is_set: Cell::new(false),
+            readers: Cell::new(0),
+            callback: Cell::new(None),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Creates an empty slot that calls `callback` whenever a value is stored.
+    pub const fn with_callback(callback: fn(&T)) -> Self {
+        WatchSlot {
+            is_set: Cell::new(false),
+            readers: Cell::new(0),
+            callback: Cell::new(Some(callback)),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Replaces the callback. `None` removes it.
+    pub fn set_callback(&self, callback: Option<fn(&T)>) {
+        self.callback.set(callback);
+    }
+
+    /// Stores `value`, returning the previously stored value, and then runs the callback.
+    ///
+    /// If the value is currently borrowed (because this is called from within the callback or
+    /// from a closure passed to [`with`]), nothing is stored and `value` is returned as an error.
+    ///
+    /// [`with`]: #method.with
+    pub fn set(&self, value: T) -> Result<Option<T>, T> {
+        if self.readers.get() != 0 {
+            return Err(value);
+        }
+
+        // Safe: there are no readers, so no references into the slot exist.
+        let old = unsafe { self.take_unchecked() };
+        unsafe { *self.slot.get() = UntaggedOption::some(value); }
+        self.is_set.set(true);
+
+        if let Some(callback) = self.callback.get() {
+            let _guard = ReadGuard::new(&self.readers);
+            callback(unsafe { (*self.slot.get()).as_ref() });
+        }
+        Ok(old)
+    }
+
+    /// Calls `f` with a reference to the stored value, if any, and returns its result.
+    pub fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(Option<&T>) -> R,
+    {
+        let _guard = ReadGuard::new(&self.readers);
+        if self.is_set.get() {
+            f(unsafe { Some((*self.slot.get()).as_ref()) })
+        } else {
+            f(None)
+        }
+    }
+
+    /// Returns a copy of the stored value, if any.
+    pub fn get(&self) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.with(|value| value.copied())
+    }
+
+    /// Removes and returns the stored value, if any. This does not run the callback.
+    pub fn take(&mut self) -> Option<T> {
+        unsafe { self.take_unchecked() }
+    }
+
+    /// Removes and returns the stored value, if any.
+    ///
+    /// # Safety
+    ///
+    /// No references into the slot may exist.
+    unsafe fn take_unchecked(&self) -> Option<T> {
+        if self.is_set.replace(false) {
+            Some((*self.slot.get()).take())
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for WatchSlot<T> {
+    fn default() -> Self {
+        WatchSlot::new()
+    }
+}
+
+impl<T> Drop for WatchSlot<T> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::thread_local;
+    use std::vec::Vec;
+
+    thread_local! {
+        static SEEN: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+        static SLOT: WatchSlot<u8> = const { WatchSlot::new() };
+    }
+
+    fn record(value: &u8) {
+        SEEN.with(|seen| seen.borrow_mut().push(*value));
+    }
+
+    fn reenter(value: &u8) {
+        record(value);
+        SLOT.with(|slot| assert_eq!(slot.set(value + 1), Err(value + 1)));
+    }
+
+    #[test]
+    fn runs_callback() {
+        let mut slot = WatchSlot::new();
+        assert_eq!(slot.set(1), Ok(None));
+        slot.set_callback(Some(record));
+        assert_eq!(slot.set(2), Ok(Some(1)));
+        assert_eq!(slot.set(3), Ok(Some(2)));
+        assert_eq!(slot.take(), Some(3));
+        SEEN.with(|seen| assert_eq!(*seen.borrow(), [2, 3]));
+    }
+
+    #[test]
+    fn refuses_reentrant_set() {
+        SLOT.with(|slot| {
+            slot.set_callback(Some(reenter));
+            assert_eq!(slot.set(5), Ok(None));
+            assert_eq!(slot.get(), Some(5));
+            slot.with(|value| assert_eq!(slot.set(value.unwrap() + 1), Err(6)));
+        });
+        SEEN.with(|seen| assert_eq!(*seen.borrow(), [5]));
+    }
+}
@@ -0,0 +1,322 @@
+//! A compact wire format for sparse [`OptionGroup`]s.
+//!
+//! An encoded group consists of the words of its presence bitmap, followed by the payloads of the
+//! occupied slots in ascending index order. Empty slots take up no space beyond their bit.
+//!
+//! Values are encoded the way [postcard] encodes them: `u8`, `i8` and `bool` as a single byte,
+//! wider integers as variable-length integers (zigzag-encoded if signed), floats as little-endian
+//! bytes, and arrays as their elements in order. A frame can therefore be decoded by a postcard
+//! deserializer reading the bitmap words followed by one value per set bit.
+//!
+//! [`OptionGroup`]: crate::OptionGroup
+//! [postcard]: https://docs.rs/postcard
+
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+use bitmap::{OptionBitmap, WORD_BITS};
+use error::WireError;
+use group::OptionGroup;
+
+/// Writes values into a byte buffer.
+#[derive(Debug)]
+pub struct Encoder<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Encoder<'a> {
+    /// Creates an encoder writing to the start of `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Encoder { buf, pos: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Writes `bytes` verbatim.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WireError> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(WireError::BufferTooSmall);
+        }
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    /// Writes `value` as a variable-length integer of 7 bits per byte, least significant first.
+    pub fn write_varint(&mut self, mut value: u64) -> Result<(), WireError> {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.write_bytes(&[byte]);
+            }
+            self.write_bytes(&[byte | 0x80])?;
+        }
+    }
+}
+
+/// Reads values from a byte buffer.
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a decoder reading from the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Decoder { bytes, pos: 0 }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the next `len` bytes.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            return Err(WireError::UnexpectedEnd);
+        }
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Reads a variable-length integer written by [`Encoder::write_varint`].
+    ///
+    /// [`Encoder::write_varint`]: crate::wire::Encoder::write_varint
+    pub fn read_varint(&mut self) -> Result<u64, WireError> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.read_bytes(1)?[0];
+            if i == 9 && byte > 1 {
+                return Err(WireError::InvalidValue);
+            }
+            value |= u64::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(WireError::InvalidValue)
+    }
+}
+
+/// Plain data that can be encoded in the wire format.
+///
+/// Implementations for custom types usually encode their fields one after another.
+pub trait WirePod: Copy {
+    /// Appends the encoding of `self` to `enc`.
+    fn encode(&self, enc: &mut Encoder) -> Result<(), WireError>;
+
+    /// Decodes a value from `dec`.
+    fn decode(dec: &mut Decoder) -> Result<Self, WireError>;
+}
+
+impl WirePod for u8 {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+        enc.write_bytes(&[*self])
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+        Ok(dec.read_bytes(1)?[0])
+    }
+}
+
+impl WirePod for i8 {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+        enc.write_bytes(&[*self as u8])
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+        Ok(dec.read_bytes(1)?[0] as i8)
+    }
+}
+
+impl WirePod for bool {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+        enc.write_bytes(&[*self as u8])
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+        match dec.read_bytes(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(WireError::InvalidValue),
+        }
+    }
+}
+
+macro_rules! wire_unsigned {
+    ($($ty:ty),*) => {$(
+        impl WirePod for $ty {
+            fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+                enc.write_varint(*self as u64)
+            }
+
+            fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+                let value = dec.read_varint()?;
+                if value > <$ty>::MAX as u64 {
+                    return Err(WireError::InvalidValue);
+                }
+                Ok(value as $ty)
+            }
+        }
+    )*};
+}
+
+macro_rules! wire_signed {
+    ($($ty:ty),*) => {$(
+        impl WirePod for $ty {
+            fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+                let value = *self as i64;
+                enc.write_varint(((value << 1) ^ (value >> 63)) as u64)
+            }
+
+            fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+                let raw = dec.read_varint()?;
+                let value = (raw >> 1) as i64 ^ -((raw & 1) as i64);
+                if value < <$ty>::MIN as i64 || value > <$ty>::MAX as i64 {
+                    return Err(WireError::InvalidValue);
+                }
+                Ok(value as $ty)
+            }
+        }
+    )*};
+}
+
+macro_rules! wire_float {
+    ($($ty:ty),*) => {$(
+        impl WirePod for $ty {
+            fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+                enc.write_bytes(&self.to_le_bytes())
+            }
+
+            fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+                let mut bytes = [0; mem::size_of::<$ty>()];
+                bytes.copy_from_slice(dec.read_bytes(mem::size_of::<$ty>())?);
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+wire_unsigned!(u16, u32, u64, usize);
+wire_signed!(i16, i32, i64, isize);
+wire_float!(f32, f64);
+
+impl<T: WirePod, const N: usize> WirePod for [T; N] {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), WireError> {
+        self.iter().try_for_each(|value| value.encode(enc))
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, WireError> {
+        let mut values = [const { MaybeUninit::<T>::uninit() }; N];
+        for value in values.iter_mut() {
+            value.write(T::decode(dec)?);
+        }
+        // Safe: every element was written above.
+        Ok(unsafe { ptr::read(values.as_ptr() as *const [T; N]) })
+    }
+}
+
+impl<T: WirePod, const N: usize, const W: usize> OptionGroup<T, N, W> {
+    /// Encodes the group into `buf`, returning the number of bytes written.
+    ///
+    /// See the [`wire`] module for the format.
+    ///
+    /// [`wire`]: crate::wire
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, WireError> {
+        let mut enc = Encoder::new(buf);
+        for &word in self.flags().words() {
+            word.encode(&mut enc)?;
+        }
+        for value in self.iter().flatten() {
+            value.encode(&mut enc)?;
+        }
+        Ok(enc.position())
+    }
+
+    /// Replaces the contents of the group with the ones decoded from `bytes`, returning the
+    /// number of bytes read.
+    ///
+    /// The payloads are decoded directly into the group's slots. If decoding fails, the group is
+    /// left holding the values decoded up to that point.
+    pub fn decode_into(&mut self, bytes: &[u8]) -> Result<usize, WireError> {
+        let mut dec = Decoder::new(bytes);
+        let mut flags = OptionBitmap::<W>::new();
+        for w in 0..W {
+            let word = u32::decode(&mut dec)?;
+            for bit in 0..WORD_BITS {
+                if word & (1 << bit) != 0 {
+                    flags.set(w * WORD_BITS + bit);
+                }
+            }
+        }
+        if flags.last_set().is_some_and(|last| last >= N) {
+            return Err(WireError::InvalidValue);
+        }
+
+        self.take_all(|_, _| {});
+        for index in flags.iter_set() {
+            self.insert(index, T::decode(&mut dec)?);
+        }
+        Ok(dec.position())
+    }
+
+    /// Decodes a group from `bytes`, returning it along with the number of bytes read.
+    pub fn decode(bytes: &[u8]) -> Result<(Self, usize), WireError> {
+        let mut group = OptionGroup::new();
+        let len = group.decode_into(bytes)?;
+        Ok((group, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut sparse = OptionGroup::<i32, 40, 2>::new();
+        sparse.insert(0, -1);
+        sparse.insert(39, 300);
+
+        let mut buf = [0; 16];
+        let len = sparse.encode(&mut buf).unwrap();
+        // Two bitmap words, then the zigzag varints of -1 and 300.
+        assert_eq!(&buf[..len], &[0x01, 0x80, 0x01, 0x01, 0xD8, 0x04]);
+
+        let (decoded, read) = OptionGroup::<i32, 40, 2>::decode(&buf[..len]).unwrap();
+        assert_eq!(read, len);
+        assert_eq!(decoded.get(0), Some(&-1));
+        assert_eq!(decoded.get(39), Some(&300));
+        assert_eq!(decoded.count(), 2);
+
+        let mut buf = [0; 8];
+        let mut enc = Encoder::new(&mut buf);
+        [300u16, 1].encode(&mut enc).unwrap();
+        let len = enc.position();
+        assert_eq!(<[u16; 2]>::decode(&mut Decoder::new(&buf[..len])), Ok([300, 1]));
+    }
+
+    #[test]
+    fn errors() {
+        let mut group = OptionGroup::<u64, 4>::new();
+        group.insert(1, u64::MAX);
+        assert_eq!(group.encode(&mut [0; 4]), Err(WireError::BufferTooSmall));
+        // A bit past the end of the group.
+        assert_eq!(OptionGroup::<u8, 4>::decode(&[0x10]).err(), Some(WireError::InvalidValue));
+        // A present slot without a payload.
+        assert_eq!(OptionGroup::<u8, 4>::decode(&[0x01]).err(), Some(WireError::UnexpectedEnd));
+        // An overlong varint.
+        let mut dec = Decoder::new(&[0xFF, 0xFF, 0x7F]);
+        assert_eq!(u16::decode(&mut dec), Err(WireError::InvalidValue));
+    }
+}
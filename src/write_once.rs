@@ -0,0 +1,200 @@
+//! A single-threaded cell that can be written once.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+
+use error::AlreadyInitialized;
+use UntaggedOption;
+
+/// A cell that can be set once and then read through shared references.
+///
+/// `WriteOnce` is meant for configuration data that is initialized once and read afterwards, on
+/// single-core systems or from a single thread. Whether the cell has been set is tracked by a
+/// one-byte flag; there are no atomics involved, so `WriteOnce` is not `Sync`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate untagged_option;
+/// # use untagged_option::WriteOnce;
+/// # fn main() {
+/// let baud_rate = WriteOnce::new();
+/// assert_eq!(baud_rate.get(), None);
+///
+/// baud_rate.set(115_200).unwrap();
+/// assert_eq!(baud_rate.get(), Some(&115_200));
+///
+/// // The cell can only be set once.
+/// assert_eq!(baud_rate.set(9600), Err(9600));
+/// # }
+/// ```
+pub struct WriteOnce<T> {
+    is_set: Cell<bool>,
+    slot: UnsafeCell<UntaggedOption<T>>,
+}
+
+impl<T> WriteOnce<T> {
+    /// Creates an empty cell.
+    pub const fn new() -> Self {
+        WriteOnce {
+            is_set: Cell::new(false),
+            slot: UnsafeCell::new(UntaggedOption::none()),
+        }
+    }
+
+    /// Returns whether the cell has been set.
+    pub fn is_set(&self) -> bool {
+        self.is_set.get()
+    }
+
+    /// Stores `value` in the cell.
+    ///
+    /// If the cell has already been set, it is left unchanged and `value` is returned as an error.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self.is_set.get() {
+            return Err(value);
+        }
+
+        // No references into the slot exist while the cell is unset.
+        unsafe { *self.slot.get() = UntaggedOption::some(value); }
+        self.is_set.set(true);
+        Ok(())
+    }
+
+    /// Stores `value` in the cell and returns a reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell has already been set.
+    pub fn init(&self, value: T) -> &T {
+        if self.set(value).is_err() {
+            panic!("`WriteOnce` set twice");
+        }
+        unsafe { (*self.slot.get()).as_ref() }
+    }
+
+    /// Stores `value` in the cell and returns a reference to it.
+    ///
+    /// This is the non-panicking version of [`init`]: if the cell has already been set, it is
+    /// left unchanged and `value` is returned in an [`AlreadyInitialized`] error.
+    ///
+    /// [`init`]: #method.init
+    /// [`AlreadyInitialized`]: crate::AlreadyInitialized
+    pub fn try_init(&self, value: T) -> Result<&T, AlreadyInitialized<T>> {
+        self.set(value).map_err(|value| AlreadyInitialized { value })?;
+        unsafe { Ok((*self.slot.get()).as_ref()) }
+    }
+
+    /// Returns a reference to the stored value, or `None` if the cell has not been set.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_set.get() {
+            unsafe { Some((*self.slot.get()).as_ref()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stored value, or stores the value returned by `f` if the cell has not been set.
+    ///
+    /// If `f` fails, the cell is left unset and its error is returned, so the initialization can
+    /// be retried later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` sets the cell itself.
+    pub fn get_or_try_init<E, F>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        match self.try_init(f()?) {
+            Ok(value) => Ok(value),
+            Err(_) => panic!("`WriteOnce` set from within `get_or_try_init`"),
+        }
+    }
+
+    /// Returns a mutable reference to the stored value, or `None` if the cell has not been set.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.is_set.get() {
+            unsafe { Some(self.slot.get_mut().as_mut()) }
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the cell and returns the stored value, if any.
+    pub fn into_inner(mut self) -> Option<T> {
+        if self.is_set.replace(false) {
+            unsafe { Some(self.slot.get_mut().take()) }
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for WriteOnce<T> {
+    fn default() -> Self {
+        WriteOnce::new()
+    }
+}
+
+impl<T> Drop for WriteOnce<T> {
+    fn drop(&mut self) {
+        if self.is_set.get() {
+            unsafe { self.slot.get_mut().take(); }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for WriteOnce<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("WriteOnce").field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    #[test]
+    fn set_once() {
+        let cell = WriteOnce::new();
+        assert!(!cell.is_set());
+        assert_eq!(*cell.init("first"), "first");
+        assert!(cell.is_set());
+        assert_eq!(cell.set("second"), Err("second"));
+        assert_eq!(cell.try_init("second"), Err(AlreadyInitialized { value: "second" }));
+        assert!(catch_unwind(AssertUnwindSafe(|| cell.init("third"))).is_err());
+        assert_eq!(cell.into_inner(), Some("first"));
+    }
+
+    #[test]
+    fn get_or_try_init() {
+        let cell = WriteOnce::new();
+        assert!(cell.get_or_try_init(|| "x".parse::<u8>()).is_err());
+        assert!(!cell.is_set());
+        assert_eq!(cell.get_or_try_init(|| "8".parse::<u8>()), Ok(&8));
+        assert_eq!(cell.get_or_try_init(|| "9".parse::<u8>()), Ok(&8));
+
+        let cell = WriteOnce::new();
+        let reentrant = catch_unwind(AssertUnwindSafe(|| {
+            cell.get_or_try_init(|| Ok::<_, ()>(*cell.init(1) + 1)).ok();
+        }));
+        assert!(reentrant.is_err());
+    }
+
+    #[test]
+    fn drops_value() {
+        let value = Rc::new(());
+        let mut cell = WriteOnce::new();
+        cell.set(value.clone()).unwrap();
+        assert!(cell.get_mut().is_some());
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(cell);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
@@ -0,0 +1,57 @@
+//! `bytemuck` trait impls, for placing options in DMA buffers and flash images.
+//!
+//! An all-zero `UntaggedOption` is a valid empty option for every policy, since the payload may
+//! hold any bytes and the policy state is zeroable, so `UntaggedOption` is [`Zeroable`]. It is
+//! [`AnyBitPattern`] when the payload and the policy state are, which allows casting byte buffers
+//! read from flash into options.
+//!
+//! `UntaggedOption` is never `Pod` or `NoUninit`, because the storage of an empty option is
+//! uninitialized. To turn a full option into bytes, cast its payload after [`as_ref`] instead.
+//!
+//! Available with the `bytemuck` feature.
+//!
+//! [`Zeroable`]: bytemuck::Zeroable
+//! [`AnyBitPattern`]: bytemuck::AnyBitPattern
+//! [`as_ref`]: crate::UntaggedOption::as_ref
+
+use bytemuck::{AnyBitPattern, Zeroable};
+use core::mem;
+
+use policy::Policy;
+use UntaggedOption;
+
+// The layout guarantees these impls rely on: the payload comes first, and the default policy adds
+// nothing to it.
+const _: () = assert!(mem::offset_of!(UntaggedOption<u32>, raw) == 0);
+const _: () = {
+    assert!(mem::size_of::<UntaggedOption<[u16; 3]>>() == mem::size_of::<[u16; 3]>());
+    assert!(mem::align_of::<UntaggedOption<u64>>() == mem::align_of::<u64>());
+};
+
+unsafe impl<T, P: Policy> Zeroable for UntaggedOption<T, P> where P::State: Zeroable {}
+
+unsafe impl<T, P> AnyBitPattern for UntaggedOption<T, P>
+where
+    T: AnyBitPattern,
+    P: Policy + 'static,
+    P::State: AnyBitPattern,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use policy::Checked;
+
+    #[test]
+    fn cast_from_bytes() {
+        let mut opt = UntaggedOption::<u32, Checked>::zeroed();
+        assert!(opt.is_none());
+        opt.write(7);
+        assert_eq!(unsafe { *opt.as_ref() }, 7);
+
+        let bytes = 0x1234_5678u32.to_ne_bytes();
+        let opt: UntaggedOption<u32> = bytemuck::pod_read_unaligned(&bytes);
+        assert_eq!(unsafe { opt.into_inner() }, 0x1234_5678);
+    }
+}